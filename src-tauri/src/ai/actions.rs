@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::ai::common::chunker::{merge_lists, merge_reports, strip_list_marker};
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{prepare_chunked_prompts, run_prompts};
+use crate::ai::error::AiError;
+use crate::ai::jobs::spawn_job;
+use crate::ai::presets::{Preset, PresetStore};
+use crate::ai::prompts::{default_prompt, render_template, resolve_system_prompt, PromptContext};
+use crate::ai::sanitize::{merge_sanitized_html, SanitizeReport};
+use crate::ai::settings::{load_ai_settings, AiCommand, ModelParams};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    // The result replaces the selected text
+    Replace,
+    // Free-form feedback shown next to the text
+    Sidebar,
+    // A list of separate items
+    List,
+}
+
+impl OutputMode {
+    // Each mode matches one built-in command, whose model settings an action
+    // starts from
+    pub fn command(self) -> AiCommand {
+        match self {
+            OutputMode::Replace => AiCommand::Proofread,
+            OutputMode::Sidebar => AiCommand::Review,
+            OutputMode::List => AiCommand::Suggestions,
+        }
+    }
+
+    // Replacement HTML is sanitized before it can reach the editor
+    fn merge(self, parts: &[String]) -> (String, Option<SanitizeReport>) {
+        match self {
+            OutputMode::Replace => {
+                let (html, report) = merge_sanitized_html(parts);
+                (html, report.into_option())
+            }
+            OutputMode::Sidebar => (merge_reports(parts), None),
+            OutputMode::List => (merge_lists(parts), None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiAction {
+    pub id: String,
+    pub name: String,
+    // A prompt template; see `render_template` for the variables
+    pub prompt: String,
+    pub output: OutputMode,
+    // Overrides on top of the AI settings for the output mode's command
+    #[serde(default)]
+    pub params: ModelParams,
+    // Built-in presets cannot be edited or deleted
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiActionResult {
+    pub action_id: String,
+    pub output: OutputMode,
+    pub result: String,
+    // Filled in for list actions
+    pub items: Vec<String>,
+    pub cached: bool,
+    // Filled in for replace actions if anything was stripped
+    pub sanitized: Option<SanitizeReport>,
+}
+
+// Proofread, suggestions and review as actions. Their prompts come from the
+// prompt library, so `prompt` here is only the built-in default.
+pub fn builtin_actions() -> Vec<AiAction> {
+    [
+        ("proofread", "Proofread", OutputMode::Replace),
+        ("suggestions", "Suggestions", OutputMode::List),
+        ("review", "Review", OutputMode::Sidebar),
+    ]
+    .into_iter()
+    .map(|(id, name, output)| AiAction {
+        id: id.to_string(),
+        name: name.to_string(),
+        prompt: default_prompt(output.command()).to_string(),
+        output,
+        params: ModelParams::default(),
+        builtin: true,
+    })
+    .collect()
+}
+
+impl Preset for AiAction {
+    const NOUN: &'static str = "AI action";
+    const FILE: &'static str = "ai_actions.json";
+
+    fn builtins() -> Vec<Self> {
+        builtin_actions()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn set_builtin(&mut self, builtin: bool) {
+        self.builtin = builtin;
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("An action needs a name.".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Items of a list response, or its paragraphs if the model ignored the format
+fn list_items(text: &str) -> Vec<String> {
+    let items: Vec<String> = text
+        .lines()
+        .filter_map(strip_list_marker)
+        .map(|item| item.to_string())
+        .collect();
+    if !items.is_empty() {
+        return items;
+    }
+
+    text.split("\n\n")
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_ai_actions(app: AppHandle) -> Result<Vec<AiAction>, String> {
+    PresetStore::<AiAction>::open(&app)?.list()
+}
+
+#[tauri::command]
+pub fn save_ai_action(app: AppHandle, action: AiAction) -> Result<AiAction, String> {
+    PresetStore::open(&app)?.upsert(action)
+}
+
+#[tauri::command]
+pub fn delete_ai_action(app: AppHandle, id: String) -> Result<(), String> {
+    PresetStore::<AiAction>::open(&app)?.delete(&id)
+}
+
+async fn run_action(
+    app: AppHandle,
+    action_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<AiActionResult, AiError> {
+    let action = list_ai_actions(app.clone())?
+        .into_iter()
+        .find(|a| a.id == action_id)
+        .ok_or_else(|| format!("AI action {} not found", action_id))?;
+
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let command = action.output.command();
+    // Keyed by id, since names can be edited and may match a built-in command
+    let scope = UsageScope::new(
+        &app,
+        &format!("run_ai_action:{}", action.id),
+        context.project_name.clone(),
+    )?;
+    let system_role_content = if action.builtin {
+        resolve_system_prompt(&app, command, &context)?
+    } else {
+        render_template(&action.prompt, &context)
+    };
+
+    let mut prompts =
+        prepare_chunked_prompts(&settings, command, &scope, &system_role_content, &content)?;
+    for prompt in &mut prompts {
+        prompt.override_params(&action.params);
+    }
+
+    let results = run_prompts(&prompts, settings.chunking.max_concurrency).await?;
+    let (result, sanitized) = action.output.merge(&results.parts);
+    let items = match action.output {
+        OutputMode::List => list_items(&result),
+        _ => Vec::new(),
+    };
+
+    Ok(AiActionResult {
+        action_id: action.id,
+        output: action.output,
+        result,
+        items,
+        cached: results.cached,
+        sanitized,
+    })
+}
+
+#[tauri::command]
+pub fn run_ai_action(
+    app: AppHandle,
+    action_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> String {
+    spawn_job(
+        &app,
+        "run_ai_action",
+        run_action(app.clone(), action_id, content, context),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_cannot_mark_action_builtin() {
+        let parsed: AiAction = serde_json::from_str(
+            r#"{"id": "x", "name": "Hook", "prompt": "p", "output": "list", "builtin": true}"#,
+        )
+        .unwrap();
+        assert!(!parsed.builtin);
+        assert_eq!(parsed.output.command(), AiCommand::Suggestions);
+    }
+
+    #[test]
+    fn test_list_items_fall_back_to_paragraphs() {
+        assert_eq!(
+            list_items("Ideas:\n- End on a question\n2. Cut the epilogue"),
+            vec!["End on a question", "Cut the epilogue"]
+        );
+        assert_eq!(
+            list_items("End on a question.\n\nCut the epilogue."),
+            vec!["End on a question.", "Cut the epilogue."]
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::ai::common::chunker::estimate_tokens;
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{complete, create_client, prepare_prompt, ChatMessage};
+use crate::ai::edits::html_to_plain_text;
+use crate::ai::error::AiError;
+use crate::ai::jobs::spawn_job;
+use crate::ai::settings::{load_ai_settings, AiCommand};
+
+const CHAT_ROLE: &str = "You are a writing assistant discussing the author's document with them. \
+Answer questions about the document and about feedback given earlier in this conversation. \
+Quote the passage you mean when it helps, and keep answers concise.";
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    // Budget for everything sent with a message: instructions, document and
+    // history. The oldest turns are left out to stay within it.
+    pub max_context_tokens: usize,
+    // Longer documents are cut short, leaving room for the conversation
+    pub max_document_tokens: usize,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        ChatSettings {
+            max_context_tokens: 8000,
+            max_document_tokens: 4000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTurn {
+    // "user" or "assistant"
+    pub role: String,
+    pub content: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub project_name: String,
+    pub file_name: String,
+    pub title: String,
+    pub created: String,
+    pub updated: String,
+    pub turns: Vec<ChatTurn>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSessionSummary {
+    pub id: String,
+    pub file_name: String,
+    pub title: String,
+    pub updated: String,
+    pub turn_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatReply {
+    pub session: ChatSession,
+    // Earlier turns left out of this request to fit the context budget.
+    // They stay in the session.
+    pub omitted_turns: usize,
+    pub cached: bool,
+}
+
+fn timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn chats_dir(app: &AppHandle, project_name: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    Ok(app_data_dir
+        .join("Projects")
+        .join(project_name)
+        .join("chats"))
+}
+
+// Session ids are generated uuids; refuse anything that could leave the
+// chats directory
+fn session_path(dir: &Path, session_id: &str) -> Result<PathBuf, String> {
+    if session_id.is_empty()
+        || !session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid chat session id: {}", session_id));
+    }
+    Ok(dir.join(format!("{}.json", session_id)))
+}
+
+fn load_session(dir: &Path, session_id: &str) -> Result<ChatSession, String> {
+    let path = session_path(dir, session_id)?;
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read chat session {}: {}", session_id, e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse chat session: {}", e))
+}
+
+fn save_session(dir: &Path, session: &ChatSession) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create chats directory: {}", e))?;
+    let raw = serde_json::to_string_pretty(session)
+        .map_err(|e| format!("Failed to serialize chat session: {}", e))?;
+    fs::write(session_path(dir, &session.id)?, raw)
+        .map_err(|e| format!("Failed to write chat session: {}", e))
+}
+
+fn title_from(message: &str) -> String {
+    let line = message.lines().next().unwrap_or("").trim();
+    if line.chars().count() <= TITLE_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(TITLE_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+// The document as plain text, cut to about `max_tokens`
+fn document_context(document: &str, max_tokens: usize) -> String {
+    let text = html_to_plain_text(document);
+    if estimate_tokens(&text) <= max_tokens {
+        return text;
+    }
+    let cut: String = text.chars().take(max_tokens * 4).collect();
+    format!("{}\n\n[The rest of the document is not shown.]", cut)
+}
+
+// How many of the oldest turns to leave out so the rest fit `budget`
+// tokens. The newest turn is always kept, and the kept history starts with
+// a user turn, since Anthropic rejects one that opens with the assistant.
+fn omitted_turn_count(turns: &[ChatTurn], budget: usize) -> usize {
+    let mut used = 0;
+    let mut kept = 0;
+    for turn in turns.iter().rev() {
+        used += estimate_tokens(&turn.content);
+        if kept > 0 && used > budget {
+            break;
+        }
+        kept += 1;
+    }
+
+    let mut omitted = turns.len() - kept;
+    while omitted + 1 < turns.len() && turns[omitted].role == "assistant" {
+        omitted += 1;
+    }
+    omitted
+}
+
+#[tauri::command]
+pub fn create_chat_session(
+    app: AppHandle,
+    project_name: String,
+    file_name: String,
+    title: Option<String>,
+) -> Result<ChatSession, String> {
+    let now = timestamp();
+    let session = ChatSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_name: project_name.clone(),
+        file_name,
+        title: title.unwrap_or_default(),
+        created: now.clone(),
+        updated: now,
+        turns: Vec::new(),
+    };
+    save_session(&chats_dir(&app, &project_name)?, &session)?;
+    Ok(session)
+}
+
+// The project's sessions, newest first, optionally only for one file
+#[tauri::command]
+pub fn list_chat_sessions(
+    app: AppHandle,
+    project_name: String,
+    file_name: Option<String>,
+) -> Result<Vec<ChatSessionSummary>, String> {
+    let dir = chats_dir(&app, &project_name)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    let read_dir = fs::read_dir(&dir).map_err(|e| format!("Failed to read chats: {}", e))?;
+    for entry in read_dir.flatten() {
+        let session: ChatSession = match fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+        {
+            Some(session) => session,
+            None => continue,
+        };
+        if file_name.as_ref().is_some_and(|f| *f != session.file_name) {
+            continue;
+        }
+        sessions.push(ChatSessionSummary {
+            id: session.id,
+            file_name: session.file_name,
+            title: session.title,
+            updated: session.updated,
+            turn_count: session.turns.len(),
+        });
+    }
+
+    sessions.sort_by(|a, b| b.updated.cmp(&a.updated));
+    Ok(sessions)
+}
+
+// A session with its full history, to resume it
+#[tauri::command]
+pub fn get_chat_session(
+    app: AppHandle,
+    project_name: String,
+    session_id: String,
+) -> Result<ChatSession, String> {
+    load_session(&chats_dir(&app, &project_name)?, &session_id)
+}
+
+#[tauri::command]
+pub fn delete_chat_session(
+    app: AppHandle,
+    project_name: String,
+    session_id: String,
+) -> Result<(), String> {
+    let path = session_path(&chats_dir(&app, &project_name)?, &session_id)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete chat session: {}", e))
+}
+
+async fn chat(
+    app: AppHandle,
+    project_name: String,
+    session_id: String,
+    message: String,
+    document: String,
+) -> Result<ChatReply, AiError> {
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        return Err("The message is empty.".to_string().into());
+    }
+
+    let settings = load_ai_settings(&app)?;
+    let dir = chats_dir(&app, &project_name)?;
+    let mut session = load_session(&dir, &session_id)?;
+    let scope = UsageScope::new(&app, "append_chat_message", Some(project_name))?;
+
+    session.turns.push(ChatTurn {
+        role: "user".to_string(),
+        content: message.clone(),
+        timestamp: timestamp(),
+    });
+
+    let document = document_context(&document, settings.chat.max_document_tokens);
+    let history_budget = settings
+        .chat
+        .max_context_tokens
+        .saturating_sub(estimate_tokens(CHAT_ROLE) + estimate_tokens(&document));
+    let omitted_turns = omitted_turn_count(&session.turns, history_budget);
+
+    // The new message is the prompt's user turn; the document and the
+    // earlier turns go in between
+    let mut prompt = prepare_prompt(&settings, AiCommand::Review, &scope, CHAT_ROLE, message)?;
+    let mut context = vec![ChatMessage::system(format!(
+        "The document being discussed, {}:\n\n{}",
+        if session.file_name.is_empty() {
+            "as it is now".to_string()
+        } else {
+            format!("\"{}\" as it is now", session.file_name)
+        },
+        prompt.redact(&document)
+    ))];
+    for turn in &session.turns[omitted_turns..session.turns.len() - 1] {
+        let content = prompt.redact(&turn.content);
+        context.push(match turn.role.as_str() {
+            "assistant" => ChatMessage::assistant(content),
+            _ => ChatMessage::user(content),
+        });
+    }
+    prompt.messages.splice(1..1, context);
+
+    let completion = complete(&create_client(), &prompt).await?;
+
+    // Saved only once answered, so a failed request can simply be resent
+    session.turns.push(ChatTurn {
+        role: "assistant".to_string(),
+        content: completion.text,
+        timestamp: timestamp(),
+    });
+    if session.title.is_empty() {
+        session.title = title_from(&session.turns[0].content);
+    }
+    session.updated = timestamp();
+    save_session(&dir, &session)?;
+
+    Ok(ChatReply {
+        session,
+        omitted_turns,
+        cached: completion.cached,
+    })
+}
+
+// Send a message in a session, with the current document as context. The
+// reply arrives on the "ai-job" event as a `ChatReply`.
+#[tauri::command]
+pub fn append_chat_message(
+    app: AppHandle,
+    project_name: String,
+    session_id: String,
+    message: String,
+    document: String,
+) -> String {
+    spawn_job(
+        &app,
+        "append_chat_message",
+        chat(app.clone(), project_name, session_id, message, document),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: &str, words: usize) -> ChatTurn {
+        ChatTurn {
+            role: role.to_string(),
+            content: "word ".repeat(words),
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_oldest_turns_are_omitted() {
+        // 25 tokens each
+        let turns = vec![
+            turn("user", 20),
+            turn("assistant", 20),
+            turn("user", 20),
+            turn("assistant", 20),
+        ];
+        assert_eq!(omitted_turn_count(&turns, 1000), 0);
+        assert_eq!(omitted_turn_count(&turns, 60), 2);
+        // The newest turn is sent even if it alone is over budget
+        assert_eq!(omitted_turn_count(&turns, 10), 3);
+    }
+
+    #[test]
+    fn test_trimmed_history_starts_with_a_user_turn() {
+        let turns = vec![
+            turn("user", 20),
+            turn("assistant", 20),
+            turn("user", 20),
+            turn("assistant", 20),
+            turn("user", 20),
+        ];
+        // Room for the last two turns, which would open with the assistant
+        let omitted = omitted_turn_count(&turns, 60);
+        assert_eq!(omitted, 4);
+        assert_eq!(turns[omitted].role, "user");
+
+        let omitted = omitted_turn_count(&turns, 80);
+        assert_eq!(omitted, 2);
+        assert_eq!(turns[omitted].role, "user");
+    }
+
+    #[test]
+    fn test_document_is_cut_to_budget() {
+        let document = format!("<p>{}</p>", "abcd".repeat(100));
+        assert_eq!(document_context(&document, 100), "abcd".repeat(100));
+
+        let cut = document_context(&document, 10);
+        assert!(cut.starts_with(&"abcd".repeat(10)));
+        assert!(cut.ends_with("[The rest of the document is not shown.]"));
+    }
+
+    #[test]
+    fn test_session_round_trip_and_ids() {
+        let dir = std::env::temp_dir().join(format!("wm9000-chat-{}", uuid::Uuid::new_v4()));
+        let session = ChatSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_name: "Novel".to_string(),
+            file_name: "Chapter 1.html".to_string(),
+            title: title_from(&"Why did you flag the second paragraph? ".repeat(3)),
+            created: timestamp(),
+            updated: timestamp(),
+            turns: vec![turn("user", 3)],
+        };
+        save_session(&dir, &session).unwrap();
+
+        let loaded = load_session(&dir, &session.id).unwrap();
+        assert_eq!(loaded.turns.len(), 1);
+        assert!(loaded.title.ends_with('…'));
+        assert!(loaded.title.chars().count() <= TITLE_CHARS + 1);
+        assert!(session_path(&dir, "../settings").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
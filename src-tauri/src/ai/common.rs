@@ -1,19 +1,858 @@
 // src-tauri/src/ai/common.rs
-use reqwest::Client;
+pub mod budget;
+pub mod cache;
+pub mod chunker;
+pub mod privacy;
+pub mod retry;
+pub mod usage;
+
+use futures_util::stream::{self, StreamExt};
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::time::Duration;
+
+use tauri::AppHandle;
+use tokio::time::sleep;
+
+use crate::ai::credentials::stored_api_key;
+use crate::ai::error::AiError;
+use crate::ai::sanitize::SanitizeReport;
+use crate::ai::settings::{AiCommand, AiSettings, ModelParams, ProviderKind};
+use cache::{cached_response, store_response, CacheSettings};
+use chunker::{split_into_chunks, Chunk};
+use privacy::Redactor;
+use retry::{backoff_delay, random_jitter, RetrySettings};
+use usage::{record_usage, TokenUsage, UsageScope};
 
 #[derive(Serialize, Deserialize)]
 pub struct OpenAIResponse {
     pub result: String,
+    // Every part of the result came from the response cache
+    #[serde(default)]
+    pub cached: bool,
+    // What was stripped from returned HTML, if anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitized: Option<SanitizeReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+// One chat completion call, independent of the provider wire format
+pub struct CompletionRequest<'a> {
+    pub model: &'a str,
+    pub params: &'a ModelParams,
+    pub messages: &'a [ChatMessage],
+    pub stream: bool,
+}
+
+// A chat completion backend. Providers only describe the wire format;
+// sending the request is shared in `complete`.
+pub trait AiProvider: Send + Sync {
+    fn id(&self) -> &'static str;
+    fn default_model(&self) -> &'static str;
+    fn build_request(&self, client: &Client, request: &CompletionRequest) -> RequestBuilder;
+    fn parse_response(&self, json: &serde_json::Value) -> Result<String, AiError>;
+    // Token counts of a non-streamed response, if the provider sent them
+    fn parse_usage(&self, json: &serde_json::Value) -> Option<TokenUsage>;
+    // Interpret the `data:` payload of one server-sent event
+    fn parse_stream_data(&self, data: &str) -> Result<StreamDelta, AiError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StreamDelta {
+    Text(String),
+    Usage(TokenUsage),
+    Done,
+    Skip,
+}
+
+pub struct OpenAiProvider {
+    api_key: String,
+    base_url: String,
+}
+
+impl AiProvider for OpenAiProvider {
+    fn id(&self) -> &'static str {
+        "openai"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gpt-3.5-turbo"
+    }
+
+    fn build_request(&self, client: &Client, request: &CompletionRequest) -> RequestBuilder {
+        let mut body = chat_completion_body(request);
+        if request.stream {
+            // Otherwise streamed responses carry no token counts
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
+        client
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+    }
+
+    fn parse_response(&self, json: &serde_json::Value) -> Result<String, AiError> {
+        parse_chat_completion(json)
+    }
+
+    fn parse_usage(&self, json: &serde_json::Value) -> Option<TokenUsage> {
+        parse_chat_usage(json)
+    }
+
+    fn parse_stream_data(&self, data: &str) -> Result<StreamDelta, AiError> {
+        parse_chat_completion_chunk(data)
+    }
+}
+
+pub struct AnthropicProvider {
+    api_key: String,
+    base_url: String,
+}
+
+impl AiProvider for AnthropicProvider {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "claude-3-5-haiku-latest"
+    }
+
+    fn build_request(&self, client: &Client, request: &CompletionRequest) -> RequestBuilder {
+        // The Messages API takes the system prompt as a top-level field
+        let system: Vec<&str> = request
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect();
+        let turns: Vec<&ChatMessage> = request
+            .messages
+            .iter()
+            .filter(|m| m.role != "system")
+            .collect();
+
+        // max_tokens is mandatory for the Messages API
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "max_tokens": request.params.max_tokens.unwrap_or(4096),
+            "messages": turns,
+            "stream": request.stream,
+        });
+        if let Some(temperature) = request.params.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = request.params.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        if !system.is_empty() {
+            body["system"] = serde_json::Value::String(system.join("\n\n"));
+        }
+
+        client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&body)
+    }
+
+    fn parse_response(&self, json: &serde_json::Value) -> Result<String, AiError> {
+        let blocks = json["content"]
+            .as_array()
+            .ok_or_else(|| AiError::malformed("missing content"))?;
+
+        Ok(blocks
+            .iter()
+            .filter(|b| b["type"] == "text")
+            .filter_map(|b| b["text"].as_str())
+            .collect::<Vec<_>>()
+            .join(""))
+    }
+
+    fn parse_usage(&self, json: &serde_json::Value) -> Option<TokenUsage> {
+        parse_anthropic_usage(&json["usage"])
+    }
+
+    fn parse_stream_data(&self, data: &str) -> Result<StreamDelta, AiError> {
+        let json: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| AiError::malformed(e.to_string()))?;
+
+        match json["type"].as_str() {
+            Some("content_block_delta") => Ok(json["delta"]["text"]
+                .as_str()
+                .map(|t| StreamDelta::Text(t.to_string()))
+                .unwrap_or(StreamDelta::Skip)),
+            // Input tokens arrive at the start, the output total at the end
+            Some("message_start") => Ok(parse_anthropic_usage(&json["message"]["usage"])
+                .map(StreamDelta::Usage)
+                .unwrap_or(StreamDelta::Skip)),
+            Some("message_delta") => Ok(parse_anthropic_usage(&json["usage"])
+                .map(StreamDelta::Usage)
+                .unwrap_or(StreamDelta::Skip)),
+            Some("message_stop") => Ok(StreamDelta::Done),
+            Some("error") => Err(AiError::from_status(
+                json["error"]["type"]
+                    .as_str()
+                    .map(anthropic_error_status)
+                    .unwrap_or(500),
+                None,
+                data,
+            )),
+            _ => Ok(StreamDelta::Skip),
+        }
+    }
+}
+
+// Ollama and llama.cpp both expose an OpenAI-compatible server
+pub struct OllamaProvider {
+    api_key: Option<String>,
+    base_url: String,
+}
+
+impl AiProvider for OllamaProvider {
+    fn id(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "llama3.1"
+    }
+
+    fn build_request(&self, client: &Client, request: &CompletionRequest) -> RequestBuilder {
+        let builder = client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&chat_completion_body(request));
+
+        match &self.api_key {
+            Some(key) => builder.header("Authorization", format!("Bearer {}", key)),
+            None => builder,
+        }
+    }
+
+    fn parse_response(&self, json: &serde_json::Value) -> Result<String, AiError> {
+        parse_chat_completion(json)
+    }
+
+    fn parse_usage(&self, json: &serde_json::Value) -> Option<TokenUsage> {
+        parse_chat_usage(json)
+    }
+
+    fn parse_stream_data(&self, data: &str) -> Result<StreamDelta, AiError> {
+        parse_chat_completion_chunk(data)
+    }
+}
+
+fn chat_completion_body(request: &CompletionRequest) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": request.model,
+        "messages": request.messages,
+        "stream": request.stream,
+    });
+    if let Some(temperature) = request.params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(max_tokens) = request.params.max_tokens {
+        body["max_tokens"] = serde_json::json!(max_tokens);
+    }
+    if let Some(top_p) = request.params.top_p {
+        body["top_p"] = serde_json::json!(top_p);
+    }
+    body
+}
+
+// Status code matching an error `type` sent mid-stream by Anthropic
+fn anthropic_error_status(error_type: &str) -> u16 {
+    match error_type {
+        "authentication_error" => 401,
+        "permission_error" => 403,
+        "rate_limit_error" => 429,
+        "invalid_request_error" => 400,
+        "overloaded_error" => 529,
+        _ => 500,
+    }
+}
+
+fn parse_chat_completion(json: &serde_json::Value) -> Result<String, AiError> {
+    json["choices"][0]["message"]["content"]
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| AiError::malformed("missing choices[0].message.content"))
+}
+
+fn parse_chat_completion_chunk(data: &str) -> Result<StreamDelta, AiError> {
+    if data.trim() == "[DONE]" {
+        return Ok(StreamDelta::Done);
+    }
+
+    let json: serde_json::Value =
+        serde_json::from_str(data).map_err(|e| AiError::malformed(e.to_string()))?;
+    // The final chunk carries usage and no choices
+    if let Some(usage) = parse_chat_usage(&json) {
+        return Ok(StreamDelta::Usage(usage));
+    }
+    Ok(json["choices"][0]["delta"]["content"]
+        .as_str()
+        .map(|t| StreamDelta::Text(t.to_string()))
+        .unwrap_or(StreamDelta::Skip))
+}
+
+fn parse_chat_usage(json: &serde_json::Value) -> Option<TokenUsage> {
+    let usage = &json["usage"];
+    Some(TokenUsage {
+        prompt_tokens: usage["prompt_tokens"].as_u64()?,
+        completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+    })
+}
+
+fn parse_anthropic_usage(usage: &serde_json::Value) -> Option<TokenUsage> {
+    if !usage.is_object() {
+        return None;
+    }
+    Some(TokenUsage {
+        prompt_tokens: usage["input_tokens"].as_u64().unwrap_or(0),
+        completion_tokens: usage["output_tokens"].as_u64().unwrap_or(0),
+    })
+}
+
+// Models often wrap JSON in a markdown code fence
+pub fn strip_code_fence(raw: &str) -> &str {
+    let trimmed = raw.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            rest.strip_suffix("```").unwrap_or(rest).trim()
+        }
+        None => trimmed,
+    }
+}
+
+// Display name and environment variable for a provider's key
+fn key_source(provider: ProviderKind) -> (&'static str, &'static str) {
+    match provider {
+        ProviderKind::OpenAi => ("OpenAI", "OPENAI_API_KEY"),
+        ProviderKind::Anthropic => ("Anthropic", "ANTHROPIC_API_KEY"),
+        ProviderKind::Ollama => ("Ollama", "OLLAMA_API_KEY"),
+    }
+}
+
+// A key saved in the app wins; the environment (.env) is the fallback
+fn find_api_key(app: &AppHandle, provider: ProviderKind) -> Result<Option<String>, AiError> {
+    let (_, env_var) = key_source(provider);
+    match stored_api_key(app, provider) {
+        Ok(Some(key)) => Ok(Some(key)),
+        Ok(None) => Ok(env::var(env_var).ok()),
+        Err(e) => env::var(env_var).map(Some).map_err(|_| e),
+    }
+}
+
+// Function to fetch a provider's API key
+pub fn get_api_key(app: &AppHandle, provider: ProviderKind) -> Result<String, AiError> {
+    find_api_key(app, provider)?.ok_or_else(|| AiError::MissingKey {
+        provider: key_source(provider).0.to_string(),
+    })
+}
+
+// Build the provider selected in the AI settings
+pub fn create_provider(
+    app: &AppHandle,
+    settings: &AiSettings,
+) -> Result<Box<dyn AiProvider>, AiError> {
+    create_provider_with_key(app, settings, None)
 }
 
-// Function to fetch the OpenAI API key
-pub fn get_api_key() -> Result<String, String> {
-    env::var("OPENAI_API_KEY").map_err(|_| "API key not set".to_string())
+// As `create_provider`, with an explicit key instead of the configured one
+pub fn create_provider_with_key(
+    app: &AppHandle,
+    settings: &AiSettings,
+    api_key: Option<String>,
+) -> Result<Box<dyn AiProvider>, AiError> {
+    let base_url = |default: &str| {
+        settings
+            .base_url
+            .as_deref()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or(default)
+            .trim_end_matches('/')
+            .to_string()
+    };
+    let api_key = api_key.filter(|key| !key.trim().is_empty());
+    let required_key = |provider| match api_key.clone() {
+        Some(key) => Ok(key),
+        None => get_api_key(app, provider),
+    };
+
+    let provider: Box<dyn AiProvider> = match settings.provider {
+        ProviderKind::OpenAi => Box::new(OpenAiProvider {
+            api_key: required_key(ProviderKind::OpenAi)?,
+            base_url: base_url("https://api.openai.com/v1"),
+        }),
+        ProviderKind::Anthropic => Box::new(AnthropicProvider {
+            api_key: required_key(ProviderKind::Anthropic)?,
+            base_url: base_url("https://api.anthropic.com/v1"),
+        }),
+        // Local servers usually need no key at all
+        ProviderKind::Ollama => Box::new(OllamaProvider {
+            api_key: match api_key.clone() {
+                Some(key) => Some(key),
+                None => find_api_key(app, ProviderKind::Ollama)?,
+            },
+            base_url: base_url("http://localhost:11434/v1"),
+        }),
+    };
+
+    Ok(provider)
 }
 
 // Initialize and return an HTTP client
 pub fn create_client() -> Client {
     Client::new()
 }
+
+// Send a prompt, retrying transient failures with exponential backoff.
+// Returns the successful response, ready to be read.
+pub async fn send_with_retry(
+    client: &Client,
+    prompt: &PreparedPrompt,
+    stream: bool,
+) -> Result<reqwest::Response, AiError> {
+    prompt.scope.check_budget()?;
+
+    let mut attempt = 0;
+    loop {
+        let error = match prompt.build_request(client, stream).send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok());
+                let body = response.text().await.unwrap_or_default();
+                AiError::from_status(status, retry_after, &body)
+            }
+            Err(e) => AiError::from_reqwest(e),
+        };
+
+        if !error.is_transient() || attempt >= prompt.retry.max_retries {
+            return Err(error);
+        }
+        match backoff_delay(
+            &prompt.retry,
+            attempt,
+            error.retry_after_secs(),
+            random_jitter(),
+        ) {
+            Some(delay) => sleep(delay).await,
+            None => return Err(error),
+        }
+        attempt += 1;
+    }
+}
+
+// Send a chat completion through the given provider and return the text
+pub async fn complete(client: &Client, prompt: &PreparedPrompt) -> Result<Completion, AiError> {
+    if let Some(text) = cached_response(prompt) {
+        return Ok(Completion {
+            text: prompt.restore(&text),
+            cached: true,
+        });
+    }
+
+    let response = send_with_retry(client, prompt, false).await?;
+
+    let json: serde_json::Value = response.json().await.map_err(AiError::from_reqwest)?;
+    let text = prompt.provider.parse_response(&json).map_err(|e| match e {
+        AiError::MalformedResponse { message } => {
+            AiError::malformed(format!("{}: {}", prompt.provider.id(), message))
+        }
+        other => other,
+    })?;
+
+    record_usage(prompt, prompt.provider.parse_usage(&json), &text);
+    // Cached as received, so the cache never holds the redacted originals
+    store_response(prompt, &text);
+    Ok(Completion {
+        text: prompt.restore(&text),
+        cached: false,
+    })
+}
+
+pub struct Completion {
+    pub text: String,
+    pub cached: bool,
+}
+
+// Results of a batch of prompts, one part per prompt in order
+pub struct PromptResults {
+    pub parts: Vec<String>,
+    // Every part came from the response cache
+    pub cached: bool,
+}
+
+impl FromIterator<Completion> for PromptResults {
+    fn from_iter<I: IntoIterator<Item = Completion>>(iter: I) -> Self {
+        let mut results = PromptResults {
+            parts: Vec::new(),
+            cached: true,
+        };
+        for completion in iter {
+            results.cached &= completion.cached;
+            results.parts.push(completion.text);
+        }
+        results
+    }
+}
+
+// Everything needed to send one prompt to the configured provider
+pub struct PreparedPrompt {
+    pub provider: Box<dyn AiProvider>,
+    pub model: String,
+    pub params: ModelParams,
+    pub messages: Vec<ChatMessage>,
+    pub retry: RetrySettings,
+    pub scope: UsageScope,
+    pub cache: CacheSettings,
+    // Set when the project has privacy mode on; messages are already
+    // redacted
+    pub redactor: Option<Redactor>,
+}
+
+impl PreparedPrompt {
+    pub fn build_request(&self, client: &Client, stream: bool) -> RequestBuilder {
+        let builder = self.provider.build_request(
+            client,
+            &CompletionRequest {
+                model: &self.model,
+                params: &self.params,
+                messages: &self.messages,
+                stream,
+            },
+        );
+
+        match self.params.timeout_secs {
+            Some(secs) => builder.timeout(Duration::from_secs(secs)),
+            None => builder,
+        }
+    }
+
+    // Put redacted originals back into response text
+    pub fn restore(&self, text: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.restore(text),
+            None => text.to_string(),
+        }
+    }
+
+    // Apply the project's privacy mode to text added to the messages
+    pub fn redact(&mut self, text: &str) -> String {
+        match &mut self.redactor {
+            Some(redactor) => redactor.redact(text),
+            None => text.to_string(),
+        }
+    }
+
+    // Apply overrides on top of the parameters the prompt was prepared with
+    pub fn override_params(&mut self, overrides: &ModelParams) {
+        self.params = overrides.or(&self.params);
+        if let Some(model) = &overrides.model {
+            self.model = model.clone();
+        }
+    }
+}
+
+pub fn prepare_prompt(
+    settings: &AiSettings,
+    command: AiCommand,
+    scope: &UsageScope,
+    system_role_content: &str,
+    content: String,
+) -> Result<PreparedPrompt, AiError> {
+    let provider = create_provider(&scope.app, settings)?;
+    prompt_with_provider(
+        provider,
+        settings,
+        command,
+        scope,
+        system_role_content,
+        content,
+        true,
+    )
+}
+
+// As `prepare_prompt`, for a provider that is already built. Without
+// `persist`, redaction placeholders are not saved to the project.
+pub fn prompt_with_provider(
+    provider: Box<dyn AiProvider>,
+    settings: &AiSettings,
+    command: AiCommand,
+    scope: &UsageScope,
+    system_role_content: &str,
+    content: String,
+    persist: bool,
+) -> Result<PreparedPrompt, AiError> {
+    let params = settings.params_for(command);
+    let model = params
+        .model
+        .clone()
+        .unwrap_or_else(|| provider.default_model().to_string());
+
+    let mut prompt = PreparedPrompt {
+        provider,
+        model,
+        params,
+        messages: Vec::new(),
+        retry: settings.retry.clone(),
+        scope: scope.clone(),
+        cache: settings.cache.clone(),
+        redactor: Redactor::for_project(&scope.app, scope.project.as_deref(), persist)?,
+    };
+    prompt.messages = vec![
+        ChatMessage::system(prompt.redact(system_role_content)),
+        ChatMessage::user(prompt.redact(&content)),
+    ];
+    Ok(prompt)
+}
+
+// Split content into chunks that fit the configured token budget
+pub fn chunk_content(settings: &AiSettings, content: &str) -> Vec<Chunk> {
+    split_into_chunks(
+        content,
+        settings.chunking.max_chunk_tokens,
+        settings.chunking.overlap_tokens,
+    )
+}
+
+pub fn prepare_chunk_prompt(
+    settings: &AiSettings,
+    command: AiCommand,
+    scope: &UsageScope,
+    system_role_content: &str,
+    chunk: &Chunk,
+) -> Result<PreparedPrompt, AiError> {
+    let mut prompt = prepare_prompt(
+        settings,
+        command,
+        scope,
+        system_role_content,
+        chunk.text.clone(),
+    )?;
+    add_chunk_context(&mut prompt, chunk);
+    Ok(prompt)
+}
+
+pub fn add_chunk_context(prompt: &mut PreparedPrompt, chunk: &Chunk) {
+    if !chunk.context.is_empty() {
+        let context = prompt.redact(&chunk.context);
+        // Preceding text goes in a separate system message so it is never
+        // mistaken for content to edit or repeat
+        prompt.messages.insert(
+            1,
+            ChatMessage::system(format!(
+                "For continuity, this is the text just before the content. \
+                 Use it for reference only; do not edit or repeat it:\n\n{}",
+                context
+            )),
+        );
+    }
+}
+
+pub fn prepare_chunked_prompts(
+    settings: &AiSettings,
+    command: AiCommand,
+    scope: &UsageScope,
+    system_role_content: &str,
+    content: &str,
+) -> Result<Vec<PreparedPrompt>, AiError> {
+    chunk_content(settings, content)
+        .iter()
+        .map(|chunk| prepare_chunk_prompt(settings, command, scope, system_role_content, chunk))
+        .collect()
+}
+
+// Run prompts with at most `max_concurrency` in flight, keeping their order
+pub async fn run_prompts(
+    prompts: &[PreparedPrompt],
+    max_concurrency: usize,
+) -> Result<PromptResults, AiError> {
+    let client = create_client();
+
+    // Iterating over indices rather than `&PreparedPrompt` keeps the future
+    // `Send`, which spawned jobs need
+    let results: Vec<Result<Completion, AiError>> = stream::iter(0..prompts.len())
+        .map(|i| complete(&client, &prompts[i]))
+        .buffered(max_concurrency.max(1))
+        .collect()
+        .await;
+
+    results.into_iter().collect()
+}
+
+// Run a system prompt over content, chunked to fit the token budget.
+// Returns one result per chunk, in order.
+pub async fn run_chunked(
+    settings: &AiSettings,
+    command: AiCommand,
+    scope: &UsageScope,
+    system_role_content: &str,
+    content: &str,
+) -> Result<PromptResults, AiError> {
+    let prompts = prepare_chunked_prompts(settings, command, scope, system_role_content, content)?;
+    run_prompts(&prompts, settings.chunking.max_concurrency).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openai_response() {
+        let provider = OpenAiProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+        };
+        let json = serde_json::json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Hello" } }]
+        });
+        assert_eq!(provider.parse_response(&json).unwrap(), "Hello");
+    }
+
+    #[test]
+    fn test_parse_anthropic_response() {
+        let provider = AnthropicProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.anthropic.com/v1".to_string(),
+        };
+        let json = serde_json::json!({
+            "content": [
+                { "type": "text", "text": "Hello " },
+                { "type": "text", "text": "world" }
+            ]
+        });
+        assert_eq!(provider.parse_response(&json).unwrap(), "Hello world");
+    }
+
+    #[test]
+    fn test_malformed_response_is_error() {
+        let provider = OllamaProvider {
+            api_key: None,
+            base_url: "http://localhost:11434/v1".to_string(),
+        };
+        let json = serde_json::json!({ "error": "model not found" });
+        assert!(provider.parse_response(&json).is_err());
+    }
+
+    #[test]
+    fn test_parse_openai_stream_chunks() {
+        let provider = OpenAiProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+        };
+        let chunk = r#"{"choices":[{"delta":{"content":"Hel"}}]}"#;
+        assert_eq!(
+            provider.parse_stream_data(chunk).unwrap(),
+            StreamDelta::Text("Hel".to_string())
+        );
+        let role_only = r#"{"choices":[{"delta":{"role":"assistant"}}]}"#;
+        assert_eq!(
+            provider.parse_stream_data(role_only).unwrap(),
+            StreamDelta::Skip
+        );
+        assert_eq!(
+            provider.parse_stream_data("[DONE]").unwrap(),
+            StreamDelta::Done
+        );
+    }
+
+    #[test]
+    fn test_parse_anthropic_stream_events() {
+        let provider = AnthropicProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.anthropic.com/v1".to_string(),
+        };
+        let delta =
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#;
+        assert_eq!(
+            provider.parse_stream_data(delta).unwrap(),
+            StreamDelta::Text("lo".to_string())
+        );
+        assert_eq!(
+            provider.parse_stream_data(r#"{"type":"ping"}"#).unwrap(),
+            StreamDelta::Skip
+        );
+        assert_eq!(
+            provider
+                .parse_stream_data(r#"{"type":"message_stop"}"#)
+                .unwrap(),
+            StreamDelta::Done
+        );
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let openai = OpenAiProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+        };
+        let json = serde_json::json!({
+            "choices": [{ "message": { "content": "Hi" } }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+        });
+        assert_eq!(
+            openai.parse_usage(&json),
+            Some(TokenUsage {
+                prompt_tokens: 12,
+                completion_tokens: 3
+            })
+        );
+        let final_chunk = r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#;
+        assert!(matches!(
+            openai.parse_stream_data(final_chunk).unwrap(),
+            StreamDelta::Usage(_)
+        ));
+
+        let anthropic = AnthropicProvider {
+            api_key: "key".to_string(),
+            base_url: "https://api.anthropic.com/v1".to_string(),
+        };
+        let start =
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":25,"output_tokens":1}}}"#;
+        assert_eq!(
+            anthropic.parse_stream_data(start).unwrap(),
+            StreamDelta::Usage(TokenUsage {
+                prompt_tokens: 25,
+                completion_tokens: 1
+            })
+        );
+    }
+}
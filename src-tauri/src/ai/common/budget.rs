@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::common::usage::{cost_of, price_for, ModelPrice, UsageRecord};
+use crate::ai::error::AiError;
+
+// A cap on tokens, dollars or both; unset means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetLimit {
+    pub max_tokens: Option<u64>,
+    pub max_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    // Applies to all usage in the current calendar month
+    pub monthly: BudgetLimit,
+    // Applies to all usage ever recorded for the project, by project name
+    pub projects: HashMap<String, BudgetLimit>,
+    // Fraction of a cap at which to warn, e.g. 0.8
+    pub warn_at: f64,
+}
+
+impl Default for BudgetSettings {
+    fn default() -> Self {
+        BudgetSettings {
+            monthly: BudgetLimit::default(),
+            projects: HashMap::new(),
+            warn_at: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Spend {
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+impl Spend {
+    fn add(&mut self, record: &UsageRecord, prices: &HashMap<String, ModelPrice>) {
+        self.tokens += record.prompt_tokens + record.completion_tokens;
+        if let Some(price) = price_for(prices, &record.model) {
+            self.cost += cost_of(price, record.prompt_tokens, record.completion_tokens);
+        }
+    }
+}
+
+impl BudgetLimit {
+    // The larger of the token and dollar fractions used, if any cap is set
+    fn used(&self, spend: &Spend) -> Option<f64> {
+        let tokens = self
+            .max_tokens
+            .map(|max| spend.tokens as f64 / max.max(1) as f64);
+        let cost = self
+            .max_cost
+            .map(|max| if max > 0.0 { spend.cost / max } else { 1.0 });
+        match (tokens, cost) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// Sent on the "ai-budget-warning" event when usage first passes `warn_at`
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetWarning {
+    // "monthly" or "project"
+    pub budget: String,
+    pub project: Option<String>,
+    pub fraction_used: f64,
+    pub spent: Spend,
+    pub limit: BudgetLimit,
+}
+
+// YYYY-MM of a ledger timestamp
+fn month_of(timestamp: &str) -> &str {
+    timestamp.get(..7).unwrap_or(timestamp)
+}
+
+// What a job's requests count against: this month's spend and, when the
+// job belongs to a project, the project's spend. Read from the ledger once
+// per job and kept up to date as its requests finish.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BudgetSpend {
+    month: String,
+    project: Option<String>,
+    monthly: Spend,
+    project_total: Spend,
+}
+
+impl BudgetSpend {
+    pub fn from_records(
+        records: &[UsageRecord],
+        prices: &HashMap<String, ModelPrice>,
+        project: Option<&str>,
+        month: &str,
+    ) -> Self {
+        let mut spend = BudgetSpend {
+            month: month.to_string(),
+            project: project.map(|p| p.to_string()),
+            ..Default::default()
+        };
+        for record in records {
+            spend.add(record, prices);
+        }
+        spend
+    }
+
+    pub fn add(&mut self, record: &UsageRecord, prices: &HashMap<String, ModelPrice>) {
+        let month = month_of(&record.timestamp);
+        if month > self.month.as_str() {
+            // A job running over midnight at month end starts the new month
+            self.month = month.to_string();
+            self.monthly = Spend::default();
+        }
+        if month == self.month {
+            self.monthly.add(record, prices);
+        }
+        if self.project.is_some() && record.project == self.project {
+            self.project_total.add(record, prices);
+        }
+    }
+
+    // Each cap that applies: (budget name, project, limit, spend)
+    fn limits<'a>(
+        &self,
+        budget: &'a BudgetSettings,
+    ) -> Vec<(&'static str, Option<String>, &'a BudgetLimit, Spend)> {
+        let mut limits = vec![("monthly", None, &budget.monthly, self.monthly)];
+        if let Some(project) = &self.project {
+            if let Some(limit) = budget.projects.get(project) {
+                limits.push(("project", Some(project.clone()), limit, self.project_total));
+            }
+        }
+        limits
+    }
+}
+
+// Refuse a new request once any cap that applies to it is used up
+pub fn check_budget(budget: &BudgetSettings, spend: &BudgetSpend) -> Result<(), AiError> {
+    for (name, project, limit, spend) in spend.limits(budget) {
+        if limit.used(&spend).is_some_and(|used| used >= 1.0) {
+            let target = match project {
+                Some(project) => format!("The budget for project \"{}\"", project),
+                None => "This month's budget".to_string(),
+            };
+            return Err(AiError::BudgetExceeded {
+                budget: name.to_string(),
+                message: format!(
+                    "{} is used up ({} tokens, ${:.2} spent).",
+                    target, spend.tokens, spend.cost
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Caps that `record` pushes past the warning threshold. `before` is the
+// spend without it, so each cap warns only once.
+pub fn crossed_warnings(
+    budget: &BudgetSettings,
+    prices: &HashMap<String, ModelPrice>,
+    before: &BudgetSpend,
+    record: &UsageRecord,
+) -> Vec<BudgetWarning> {
+    let mut after = before.clone();
+    after.add(record, prices);
+
+    before
+        .limits(budget)
+        .into_iter()
+        .zip(after.limits(budget))
+        .filter_map(|((_, _, _, spent_before), (name, project, limit, spent))| {
+            let used_before = limit.used(&spent_before)?;
+            let used = limit.used(&spent)?;
+            (used_before < budget.warn_at && used >= budget.warn_at).then(|| BudgetWarning {
+                budget: name.to_string(),
+                project,
+                fraction_used: used,
+                spent,
+                limit: limit.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: &str, project: Option<&str>, tokens: u64) -> UsageRecord {
+        UsageRecord {
+            timestamp: timestamp.to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-3.5-turbo".to_string(),
+            command: "ai_review".to_string(),
+            project: project.map(|p| p.to_string()),
+            prompt_tokens: tokens,
+            completion_tokens: 0,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_monthly_cap_ignores_earlier_months() {
+        let budget = BudgetSettings {
+            monthly: BudgetLimit {
+                max_tokens: Some(1000),
+                max_cost: None,
+            },
+            ..Default::default()
+        };
+        let prices = HashMap::new();
+        let records = vec![
+            record("2026-02-27 10:00:00", None, 5000),
+            record("2026-03-01 10:00:00", None, 600),
+        ];
+        let mut spend = BudgetSpend::from_records(&records, &prices, None, "2026-03");
+        assert!(check_budget(&budget, &spend).is_ok());
+
+        spend.add(&record("2026-03-02 10:00:00", None, 400), &prices);
+        let error = check_budget(&budget, &spend).unwrap_err();
+        assert_eq!(error.kind(), "budget_exceeded");
+    }
+
+    #[test]
+    fn test_project_dollar_cap() {
+        let mut budget = BudgetSettings::default();
+        budget.projects.insert(
+            "Novel".to_string(),
+            BudgetLimit {
+                max_tokens: None,
+                max_cost: Some(1.0),
+            },
+        );
+        let prices = HashMap::new();
+        // Two million prompt tokens of gpt-3.5-turbo is $1.00
+        let records = vec![record("2026-03-01 10:00:00", Some("Novel"), 2_000_000)];
+
+        let novel = BudgetSpend::from_records(&records, &prices, Some("Novel"), "2026-03");
+        let poems = BudgetSpend::from_records(&records, &prices, Some("Poems"), "2026-03");
+        assert!(check_budget(&budget, &novel).is_err());
+        assert!(check_budget(&budget, &poems).is_ok());
+    }
+
+    #[test]
+    fn test_warning_fires_once_when_crossing_threshold() {
+        let budget = BudgetSettings {
+            monthly: BudgetLimit {
+                max_tokens: Some(1000),
+                max_cost: None,
+            },
+            ..Default::default()
+        };
+        let prices = HashMap::new();
+        let records = vec![record("2026-03-01 10:00:00", None, 700)];
+        let mut spend = BudgetSpend::from_records(&records, &prices, None, "2026-03");
+
+        let crossing = record("2026-03-01 11:00:00", None, 200);
+        let warnings = crossed_warnings(&budget, &prices, &spend, &crossing);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].budget, "monthly");
+        assert_eq!(warnings[0].spent.tokens, 900);
+
+        spend.add(&crossing, &prices);
+        let later = record("2026-03-01 12:00:00", None, 50);
+        assert!(crossed_warnings(&budget, &prices, &spend, &later).is_empty());
+    }
+
+    #[test]
+    fn test_running_spend_starts_a_new_month() {
+        let budget = BudgetSettings {
+            monthly: BudgetLimit {
+                max_tokens: Some(1000),
+                max_cost: None,
+            },
+            ..Default::default()
+        };
+        let prices = HashMap::new();
+        let records = vec![record("2026-03-31 23:00:00", None, 1000)];
+        let mut spend = BudgetSpend::from_records(&records, &prices, None, "2026-03");
+        assert!(check_budget(&budget, &spend).is_err());
+
+        spend.add(&record("2026-04-01 00:01:00", None, 10), &prices);
+        assert!(check_budget(&budget, &spend).is_ok());
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::ai::common::PreparedPrompt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    pub ttl_hours: u64,
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            enabled: true,
+            ttl_hours: 24 * 30,
+            max_size_mb: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    provider: String,
+    model: String,
+    template_hash: String,
+    content_hash: String,
+    // Unix seconds
+    created: u64,
+    response: String,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn sha256_hex(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // Keep ("ab", "c") and ("a", "bc") apart
+        hasher.update([0u8]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    Ok(app_data_dir.join("Cache").join("ai"))
+}
+
+// The system messages (the rendered template and any chunk context) and
+// the model parameters hash to the template; user messages to the content
+fn prompt_hashes(prompt: &PreparedPrompt) -> (String, String) {
+    let system: Vec<&str> = prompt
+        .messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| m.content.as_str())
+        .collect();
+    let content: Vec<&str> = prompt
+        .messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .map(|m| m.content.as_str())
+        .collect();
+
+    let params = serde_json::to_string(&prompt.params).unwrap_or_default();
+    let mut template = system;
+    template.push(&params);
+    (sha256_hex(&template), sha256_hex(&content))
+}
+
+// File name for the entry keyed by (provider, model, template, content)
+fn entry_key(prompt: &PreparedPrompt) -> (String, CacheEntry) {
+    let (template_hash, content_hash) = prompt_hashes(prompt);
+    let entry = CacheEntry {
+        provider: prompt.provider.id().to_string(),
+        model: prompt.model.clone(),
+        template_hash,
+        content_hash,
+        created: now_secs(),
+        response: String::new(),
+    };
+    let key = sha256_hex(&[
+        &entry.provider,
+        &entry.model,
+        &entry.template_hash,
+        &entry.content_hash,
+    ]);
+    (key, entry)
+}
+
+fn read_entry(dir: &Path, key: &str, ttl_secs: u64, now: u64) -> Option<String> {
+    let raw = fs::read_to_string(dir.join(format!("{}.json", key))).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&raw).ok()?;
+    (now.saturating_sub(entry.created) <= ttl_secs).then_some(entry.response)
+}
+
+// Remove expired entries, then the oldest until the cache fits `max_bytes`
+fn prune(dir: &Path, ttl_secs: u64, max_bytes: u64, now: u64) -> Result<(), String> {
+    let mut entries: Vec<(PathBuf, u64, u64)> = Vec::new();
+    let read_dir = fs::read_dir(dir).map_err(|e| format!("Failed to read AI cache: {}", e))?;
+    for item in read_dir.flatten() {
+        let path = item.path();
+        let size = item.metadata().map(|m| m.len()).unwrap_or(0);
+        let created = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<CacheEntry>(&raw).ok())
+            .map(|entry| entry.created);
+
+        match created {
+            Some(created) if now.saturating_sub(created) <= ttl_secs => {
+                entries.push((path, size, created))
+            }
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
+    entries.sort_by_key(|(_, _, created)| *created);
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    for (path, size, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        let _ = fs::remove_file(&path);
+        total -= size;
+    }
+    Ok(())
+}
+
+fn write_entry(dir: &Path, key: &str, entry: &CacheEntry) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create AI cache: {}", e))?;
+    let raw = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+    fs::write(dir.join(format!("{}.json", key)), raw)
+        .map_err(|e| format!("Failed to write AI cache: {}", e))
+}
+
+pub fn cached_response(prompt: &PreparedPrompt) -> Option<String> {
+    if !prompt.cache.enabled {
+        return None;
+    }
+    let dir = cache_dir(&prompt.scope.app).ok()?;
+    let (key, _) = entry_key(prompt);
+    read_entry(&dir, &key, prompt.cache.ttl_hours * 3600, now_secs())
+}
+
+// Failing to cache is never worth failing the request over
+pub fn store_response(prompt: &PreparedPrompt, response: &str) {
+    if !prompt.cache.enabled {
+        return;
+    }
+    let dir = match cache_dir(&prompt.scope.app) {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+
+    let (key, mut entry) = entry_key(prompt);
+    entry.response = response.to_string();
+    if write_entry(&dir, &key, &entry).is_ok() {
+        let _ = prune(
+            &dir,
+            prompt.cache.ttl_hours * 3600,
+            prompt.cache.max_size_mb * 1024 * 1024,
+            entry.created,
+        );
+    }
+}
+
+// Returns the number of cached responses removed
+#[tauri::command]
+pub fn clear_ai_cache(app: AppHandle) -> Result<usize, String> {
+    let dir = cache_dir(&app)?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let count = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read AI cache: {}", e))?
+        .count();
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear AI cache: {}", e))?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(created: u64, response: &str) -> CacheEntry {
+        CacheEntry {
+            provider: "openai".to_string(),
+            model: "gpt-3.5-turbo".to_string(),
+            template_hash: sha256_hex(&["Review this:"]),
+            content_hash: sha256_hex(&["<p>Chapter one</p>"]),
+            created,
+            response: response.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hash_separates_parts() {
+        assert_ne!(sha256_hex(&["ab", "c"]), sha256_hex(&["a", "bc"]));
+        assert_eq!(sha256_hex(&["a"]).len(), 64);
+    }
+
+    #[test]
+    fn test_entries_expire_and_prune_oldest() {
+        let dir = std::env::temp_dir().join(format!("wm9000-cache-{}", uuid::Uuid::new_v4()));
+        write_entry(&dir, "old", &entry(1_000, "Old review")).unwrap();
+        write_entry(&dir, "new", &entry(5_000, "New review")).unwrap();
+
+        assert_eq!(
+            read_entry(&dir, "new", 3_600, 6_000),
+            Some("New review".to_string())
+        );
+        assert_eq!(read_entry(&dir, "old", 3_600, 6_000), None);
+        assert_eq!(read_entry(&dir, "missing", 3_600, 6_000), None);
+
+        // Room for one entry only: the newer one survives
+        let size = fs::metadata(dir.join("new.json")).unwrap().len();
+        prune(&dir, 10_000, size, 6_000).unwrap();
+        assert!(!dir.join("old.json").exists());
+        assert!(dir.join("new.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "blockquote",
+    "pre",
+    "div",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkSettings {
+    pub max_chunk_tokens: usize,
+    pub overlap_tokens: usize,
+    // 1 runs chunks one after another
+    pub max_concurrency: usize,
+}
+
+impl Default for ChunkSettings {
+    fn default() -> Self {
+        ChunkSettings {
+            max_chunk_tokens: 3000,
+            overlap_tokens: 200,
+            max_concurrency: 1,
+        }
+    }
+}
+
+// A slice of the source sent as one request. `context` is the tail of the
+// previous chunk, given to the model for continuity but not to be edited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub index: usize,
+    // Byte offset of `text` in the source
+    pub offset: usize,
+    pub text: String,
+    pub context: String,
+}
+
+// Rough token estimate; about four characters per token for English prose
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn split_into_chunks(content: &str, max_tokens: usize, overlap_tokens: usize) -> Vec<Chunk> {
+    let max_tokens = max_tokens.max(1);
+    if estimate_tokens(content) <= max_tokens {
+        return vec![Chunk {
+            index: 0,
+            offset: 0,
+            text: content.to_string(),
+            context: String::new(),
+        }];
+    }
+
+    let blocks: Vec<Range<usize>> = paragraph_ranges(content)
+        .into_iter()
+        .flat_map(|range| split_oversized(content, range, max_tokens))
+        .collect();
+
+    // Greedily pack whole blocks into chunks, as index ranges into `blocks`
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut first = 0;
+    let mut tokens = 0;
+    for (i, block) in blocks.iter().enumerate() {
+        let block_tokens = estimate_tokens(&content[block.clone()]);
+        if i > first && tokens + block_tokens > max_tokens {
+            groups.push(first..i);
+            first = i;
+            tokens = 0;
+        }
+        tokens += block_tokens;
+    }
+    groups.push(first..blocks.len());
+
+    groups
+        .iter()
+        .enumerate()
+        .map(|(index, group)| {
+            let start = blocks[group.start].start;
+            let end = blocks[group.end - 1].end;
+            Chunk {
+                index,
+                offset: start,
+                text: content[start..end].to_string(),
+                context: overlap_context(content, &blocks[..group.start], overlap_tokens),
+            }
+        })
+        .collect()
+}
+
+fn overlap_context(content: &str, preceding: &[Range<usize>], overlap_tokens: usize) -> String {
+    if overlap_tokens == 0 || preceding.is_empty() {
+        return String::new();
+    }
+
+    let end = preceding[preceding.len() - 1].end;
+    let mut start = end;
+    let mut tokens = 0;
+    for block in preceding.iter().rev() {
+        let block_tokens = estimate_tokens(&content[block.clone()]);
+        if tokens + block_tokens > overlap_tokens {
+            break;
+        }
+        tokens += block_tokens;
+        start = block.start;
+    }
+
+    if start == end {
+        // The previous block alone is too long; fall back to its tail
+        let mut tail = end.saturating_sub(overlap_tokens * 4);
+        while !content.is_char_boundary(tail) {
+            tail += 1;
+        }
+        start = tail;
+    }
+
+    content[start..end].to_string()
+}
+
+// Split HTML after top-level block tags, or plain text after each line
+fn paragraph_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut boundaries: Vec<usize> = Vec::new();
+
+    if content.contains("</") {
+        let lower = content.to_ascii_lowercase();
+        let mut depth = 0usize;
+        let mut pos = 0;
+        while let Some(found) = lower[pos..].find('<') {
+            let tag_start = pos + found;
+            let tag_end = match lower[tag_start..].find('>') {
+                Some(end) => tag_start + end + 1,
+                None => break,
+            };
+            let tag = &lower[tag_start + 1..tag_end - 1];
+            let closing = tag.starts_with('/');
+            let name: String = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+
+            if BLOCK_TAGS.contains(&name.as_str()) && !tag.ends_with('/') {
+                if closing {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        boundaries.push(tag_end);
+                    }
+                } else {
+                    depth += 1;
+                }
+            }
+            pos = tag_end;
+        }
+    } else {
+        boundaries.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+    }
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for end in boundaries {
+        if end > start {
+            ranges.push(start..end);
+            start = end;
+        }
+    }
+    if start < content.len() {
+        // Trailing whitespace joins the last block instead of standing alone
+        match ranges.last_mut() {
+            Some(last) if content[start..].trim().is_empty() => last.end = content.len(),
+            _ => ranges.push(start..content.len()),
+        }
+    }
+    ranges
+}
+
+// Break a block that is too large on its own, preferring sentence and
+// word boundaries
+fn split_oversized(content: &str, range: Range<usize>, max_tokens: usize) -> Vec<Range<usize>> {
+    let max_bytes = max_tokens * 4;
+    let mut pieces = Vec::new();
+    let mut start = range.start;
+
+    while estimate_tokens(&content[start..range.end]) > max_tokens {
+        let mut limit = (start + max_bytes).min(range.end);
+        while !content.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        let window = &content[start..limit];
+        let mut cut = window
+            .rfind(". ")
+            .map(|i| i + 2)
+            .or_else(|| window.rfind(' ').map(|i| i + 1))
+            .filter(|&i| i > 0)
+            .unwrap_or(window.len());
+        // Never cut inside a tag: back up to just before it, or take the
+        // whole tag if it starts the piece
+        let head = &window[..cut];
+        if let Some(open) = head.rfind('<').filter(|&i| !head[i..].contains('>')) {
+            cut = if open > 0 {
+                open
+            } else {
+                content[start..range.end]
+                    .find('>')
+                    .map_or(range.end - start, |close| close + 1)
+            };
+        }
+        pieces.push(start..start + cut);
+        start += cut;
+    }
+    pieces.push(start..range.end);
+    pieces
+}
+
+// Proofread chunks are contiguous HTML and simply concatenate
+pub fn merge_html(parts: &[String]) -> String {
+    parts.iter().map(|p| p.trim()).collect::<Vec<_>>().join("")
+}
+
+pub fn strip_list_marker(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    for marker in ["- ", "* ", "• "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return Some(rest.trim());
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some(rest.trim());
+        }
+    }
+    None
+}
+
+// Suggestions from each chunk are merged into one deduplicated list
+pub fn merge_lists(parts: &[String]) -> String {
+    if parts.len() == 1 {
+        return parts[0].clone();
+    }
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut items: Vec<String> = Vec::new();
+
+    for part in parts {
+        let list_items: Vec<&str> = part.lines().filter_map(strip_list_marker).collect();
+        let part_items: Vec<&str> = if list_items.is_empty() {
+            // Not a list; keep each paragraph as an item
+            part.split("\n\n")
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .collect()
+        } else {
+            list_items
+        };
+
+        for item in part_items {
+            if seen.insert(item.to_lowercase()) {
+                items.push(format!("- {}", item));
+            }
+        }
+    }
+
+    items.join("\n")
+}
+
+// Reviews of each chunk become sections of one report
+pub fn merge_reports(parts: &[String]) -> String {
+    if parts.len() == 1 {
+        return parts[0].clone();
+    }
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| format!("## Part {} of {}\n\n{}", i + 1, parts.len(), part.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_content_is_one_chunk() {
+        let chunks = split_into_chunks("<p>Short</p>", 100, 10);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "<p>Short</p>");
+        assert!(chunks[0].context.is_empty());
+    }
+
+    #[test]
+    fn test_html_splits_on_paragraphs_with_overlap() {
+        let para = format!("<p>{}</p>", "word ".repeat(20));
+        let content = para.repeat(6);
+        let chunks = split_into_chunks(&content, 60, 40);
+
+        assert!(chunks.len() > 1);
+        // Chunks tile the source exactly, each on a paragraph boundary
+        let rebuilt: String = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(rebuilt, content);
+        for chunk in &chunks {
+            assert!(chunk.text.starts_with("<p>"));
+            assert!(chunk.text.ends_with("</p>"));
+            assert_eq!(
+                &content[chunk.offset..chunk.offset + chunk.text.len()],
+                chunk.text
+            );
+        }
+        assert!(chunks[0].context.is_empty());
+        assert_eq!(chunks[1].context, para);
+    }
+
+    #[test]
+    fn test_lists_are_not_split() {
+        let item = format!("<li>{}</li>", "item ".repeat(10));
+        let content = format!("<p>Intro</p><ul>{}</ul><p>End</p>", item.repeat(3));
+        let ranges = paragraph_ranges(&content);
+        assert_eq!(ranges.len(), 3);
+        assert!(content[ranges[1].clone()].starts_with("<ul>"));
+    }
+
+    #[test]
+    fn test_oversized_paragraph_split_on_sentences() {
+        let content = "One sentence here. ".repeat(20);
+        let chunks = split_into_chunks(&content, 20, 0);
+        assert!(chunks.len() > 1);
+        let rebuilt: String = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(rebuilt, content);
+        assert!(chunks[0].text.ends_with(". "));
+    }
+
+    #[test]
+    fn test_oversized_paragraph_never_splits_a_tag() {
+        // The only spaces are inside tags, so a word-boundary cut lands in one
+        let content = format!(
+            "<p>{}</p>",
+            "<span class=\"ql-size-large ql-font-serif\">Word</span><em>and</em>".repeat(30)
+        );
+        let chunks = split_into_chunks(&content, 20, 0);
+        assert!(chunks.len() > 1);
+        let rebuilt: String = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(rebuilt, content);
+        for chunk in &chunks {
+            let opens = chunk.text.matches('<').count();
+            assert_eq!(opens, chunk.text.matches('>').count(), "{}", chunk.text);
+            assert!(!chunk.text.ends_with('<'));
+            assert!(chunk.text.rfind('<') < chunk.text.rfind('>'));
+        }
+    }
+
+    #[test]
+    fn test_merge_lists_dedupes() {
+        let merged = merge_lists(&[
+            "Suggestions:\n1. Vary sentence length\n2. Cut adverbs".to_string(),
+            "- cut adverbs\n- Show, don't tell".to_string(),
+        ]);
+        assert_eq!(
+            merged,
+            "- Vary sentence length\n- Cut adverbs\n- Show, don't tell"
+        );
+    }
+
+    #[test]
+    fn test_merge_reports_adds_part_headings() {
+        let merged = merge_reports(&["Good pacing.".to_string(), "Weak ending.".to_string()]);
+        assert!(merged.starts_with("## Part 1 of 2\n\nGood pacing."));
+        assert!(merged.contains("## Part 2 of 2\n\nWeak ending."));
+    }
+}
@@ -0,0 +1,451 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const PRIVACY_FILE: &str = "privacy.json";
+// Longer than any placeholder, e.g. "[PERSON_12345]"
+const MAX_PLACEHOLDER_LEN: usize = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    pub enabled: bool,
+    // Character names and places to hide, matched as whole words
+    pub names: Vec<String>,
+    pub places: Vec<String>,
+    pub detect_emails: bool,
+    pub detect_phones: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        PrivacySettings {
+            enabled: false,
+            names: Vec::new(),
+            places: Vec::new(),
+            detect_emails: true,
+            detect_phones: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectPrivacy {
+    pub settings: PrivacySettings,
+    // Original text to placeholder. Kept so a name gets the same
+    // placeholder in every request for the project.
+    pub placeholders: BTreeMap<String, String>,
+}
+
+fn privacy_path(app: &AppHandle, project_name: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    Ok(app_data_dir
+        .join("Projects")
+        .join(project_name)
+        .join(PRIVACY_FILE))
+}
+
+fn load_privacy(path: &Path) -> Result<ProjectPrivacy, String> {
+    if !path.exists() {
+        return Ok(ProjectPrivacy::default());
+    }
+    let raw =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read privacy settings: {}", e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse privacy settings: {}", e))
+}
+
+fn save_privacy(path: &Path, privacy: &ProjectPrivacy) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create project directory: {}", e))?;
+    }
+    let raw = serde_json::to_string_pretty(privacy)
+        .map_err(|e| format!("Failed to serialize privacy settings: {}", e))?;
+    fs::write(path, raw).map_err(|e| format!("Failed to write privacy settings: {}", e))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_email_local(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._%+-".contains(c)
+}
+
+fn is_email_domain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '-'
+}
+
+// Addresses like "name@example.com", by byte range
+fn find_emails(text: &str) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut searched = 0;
+
+    while let Some(at) = text[searched..].find('@').map(|pos| pos + searched) {
+        searched = at + 1;
+        let start = text[..at]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_email_local(*c))
+            .last()
+            .map(|(i, _)| i);
+        let domain = &text[at + 1..];
+        let domain_len = domain
+            .find(|c: char| !is_email_domain(c))
+            .unwrap_or(domain.len());
+        let domain = domain[..domain_len].trim_end_matches(['.', '-']);
+
+        let start = match start {
+            Some(start) if start < at => start,
+            _ => continue,
+        };
+        let labels: Vec<&str> = domain.split('.').collect();
+        if labels.len() < 2 || labels.iter().any(|label| label.is_empty()) {
+            continue;
+        }
+        let end = at + 1 + domain.len();
+        found.push((start, end));
+        searched = end;
+    }
+    found
+}
+
+// Runs of 7 to 15 digits with the usual separators, e.g. "+1 (555) 010-0199"
+fn find_phones(text: &str) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let at_boundary = i == 0 || !is_word_char(chars[i - 1].1);
+        if !(at_boundary && (c.is_ascii_digit() || c == '+' || c == '(')) {
+            i += 1;
+            continue;
+        }
+
+        let mut digits = 0;
+        let mut last_digit = None;
+        let mut j = i;
+        while j < chars.len() {
+            let c = chars[j].1;
+            if c.is_ascii_digit() {
+                digits += 1;
+                last_digit = Some(j);
+            } else if !(" -.()".contains(c) || (c == '+' && j == i)) {
+                break;
+            }
+            j += 1;
+        }
+
+        match last_digit {
+            Some(last)
+                if (7..=15).contains(&digits)
+                    && !matches!(chars.get(last + 1), Some((_, c)) if is_word_char(*c)) =>
+            {
+                let end = chars[last].0 + 1;
+                found.push((start, end));
+                i = last + 1;
+            }
+            _ => i = j.max(i + 1),
+        }
+    }
+    found
+}
+
+// Swaps private terms for placeholders before a request and back again in
+// the response
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    settings: PrivacySettings,
+    placeholders: BTreeMap<String, String>,
+    // Where new placeholders are saved; None for previews and tests
+    path: Option<PathBuf>,
+}
+
+impl Redactor {
+    fn new(privacy: ProjectPrivacy, path: Option<PathBuf>) -> Self {
+        Redactor {
+            settings: privacy.settings,
+            placeholders: privacy.placeholders,
+            path,
+        }
+    }
+
+    // The redactor for the scope's project, if it has privacy mode enabled.
+    // Without `persist`, new placeholders are kept in memory only, so a
+    // preview leaves the project's mapping untouched.
+    pub fn for_project(
+        app: &AppHandle,
+        project: Option<&str>,
+        persist: bool,
+    ) -> Result<Option<Self>, String> {
+        match project {
+            Some(project) => Redactor::load(&privacy_path(app, project)?, persist),
+            None => Ok(None),
+        }
+    }
+
+    fn load(path: &Path, persist: bool) -> Result<Option<Self>, String> {
+        let privacy = load_privacy(path)?;
+        Ok(privacy
+            .settings
+            .enabled
+            .then(|| Redactor::new(privacy, persist.then(|| path.to_path_buf()))))
+    }
+
+    fn placeholder_for(&mut self, original: &str, kind: &str) -> String {
+        if let Some(placeholder) = self.placeholders.get(original) {
+            return placeholder.clone();
+        }
+        let prefix = format!("[{}_", kind);
+        let next = self
+            .placeholders
+            .values()
+            .filter(|p| p.starts_with(&prefix))
+            .count()
+            + 1;
+        let placeholder = format!("{}{}]", prefix, next);
+        self.placeholders
+            .insert(original.to_string(), placeholder.clone());
+        placeholder
+    }
+
+    // Replace configured names and places, and any detected emails and
+    // phone numbers, with placeholders
+    pub fn redact(&mut self, text: &str) -> String {
+        let known = self.placeholders.len();
+
+        let mut terms: Vec<(String, String)> = Vec::new();
+        let configured = [
+            (self.settings.names.clone(), "PERSON"),
+            (self.settings.places.clone(), "PLACE"),
+        ];
+        for (list, kind) in configured {
+            for term in list.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                let placeholder = self.placeholder_for(term, kind);
+                terms.push((term.to_string(), placeholder));
+            }
+        }
+
+        let mut detected = Vec::new();
+        if self.settings.detect_emails {
+            detected.extend(find_emails(text).into_iter().map(|r| (r, "EMAIL")));
+        }
+        if self.settings.detect_phones {
+            detected.extend(find_phones(text).into_iter().map(|r| (r, "PHONE")));
+        }
+        for ((start, end), kind) in detected {
+            let term = &text[start..end];
+            let placeholder = self.placeholder_for(term, kind);
+            terms.push((term.to_string(), placeholder));
+        }
+
+        if self.placeholders.len() > known {
+            self.save();
+        }
+
+        // Longest first, so "New York City" wins over "New York"
+        terms.sort_by_key(|(term, _)| Reverse(term.len()));
+        replace_words(text, &terms)
+    }
+
+    // Put the originals back in place of any placeholders
+    pub fn restore(&self, text: &str) -> String {
+        let mut restored = text.to_string();
+        for (original, placeholder) in &self.placeholders {
+            if restored.contains(placeholder.as_str()) {
+                restored = restored.replace(placeholder.as_str(), original);
+            }
+        }
+        restored
+    }
+
+    // Restore streamed text as it arrives. A placeholder split across
+    // deltas is held in `pending` until the rest of it arrives.
+    pub fn restore_partial(&self, pending: &mut String, delta: &str) -> String {
+        pending.push_str(delta);
+        let split = match pending.rfind('[') {
+            Some(open)
+                if !pending[open..].contains(']') && pending.len() - open < MAX_PLACEHOLDER_LEN =>
+            {
+                open
+            }
+            _ => pending.len(),
+        };
+        let ready: String = pending.drain(..split).collect();
+        self.restore(&ready)
+    }
+
+    // Failing to save only costs placeholder stability across requests;
+    // this request restores from the mapping in memory
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            let privacy = ProjectPrivacy {
+                settings: self.settings.clone(),
+                placeholders: self.placeholders.clone(),
+            };
+            let _ = save_privacy(path, &privacy);
+        }
+    }
+}
+
+// Replace whole-word occurrences of each term, trying terms in order
+fn replace_words(text: &str, terms: &[(String, String)]) -> String {
+    if terms.is_empty() {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let at_boundary = !matches!(text[..i].chars().next_back(), Some(c) if is_word_char(c));
+        let matched = terms.iter().find(|(term, _)| {
+            at_boundary
+                && text[i..].starts_with(term.as_str())
+                && !matches!(text[i + term.len()..].chars().next(), Some(c) if is_word_char(c))
+        });
+
+        match matched {
+            Some((term, placeholder)) => {
+                result.push_str(placeholder);
+                i += term.len();
+            }
+            None => {
+                let c = text[i..].chars().next().unwrap_or_default();
+                result.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    result
+}
+
+#[tauri::command]
+pub fn get_privacy_settings(
+    app: AppHandle,
+    project_name: String,
+) -> Result<ProjectPrivacy, String> {
+    load_privacy(&privacy_path(&app, &project_name)?)
+}
+
+// Placeholders already assigned are kept, so earlier responses and cached
+// results still restore
+#[tauri::command]
+pub fn save_privacy_settings(
+    app: AppHandle,
+    project_name: String,
+    settings: PrivacySettings,
+) -> Result<(), String> {
+    let path = privacy_path(&app, &project_name)?;
+    let mut privacy = load_privacy(&path)?;
+    privacy.settings = settings;
+    save_privacy(&path, &privacy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(names: &[&str], places: &[&str]) -> Redactor {
+        Redactor::new(
+            ProjectPrivacy {
+                settings: PrivacySettings {
+                    enabled: true,
+                    names: names.iter().map(|n| n.to_string()).collect(),
+                    places: places.iter().map(|p| p.to_string()).collect(),
+                    ..Default::default()
+                },
+                placeholders: BTreeMap::new(),
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_redact_and_restore_round_trip() {
+        let mut redactor = redactor(&["Mara", "Mara Voss"], &["Eldham"]);
+        let text = "<p>Mara Voss left Eldham. Mara's sister, Maradine, stayed.</p>";
+        let redacted = redactor.redact(text);
+
+        assert_eq!(
+            redacted,
+            "<p>[PERSON_2] left [PLACE_1]. [PERSON_1]'s sister, Maradine, stayed.</p>"
+        );
+        assert_eq!(redactor.restore(&redacted), text);
+    }
+
+    #[test]
+    fn test_placeholders_are_stable() {
+        let mut redactor = redactor(&["Mara"], &[]);
+        let first = redactor.redact("Write to mara@example.com.");
+        let second = redactor.redact("Mara wrote back from mara@example.com");
+
+        assert_eq!(first, "Write to [EMAIL_1].");
+        assert_eq!(second, "[PERSON_1] wrote back from [EMAIL_1]");
+    }
+
+    #[test]
+    fn test_detects_emails_and_phones() {
+        let text = "Call +1 (555) 010-0199 by 1999, or mail j.doe+wm@mail.example.org.";
+        let emails: Vec<&str> = find_emails(text)
+            .iter()
+            .map(|&(s, e)| &text[s..e])
+            .collect();
+        let phones: Vec<&str> = find_phones(text)
+            .iter()
+            .map(|&(s, e)| &text[s..e])
+            .collect();
+
+        assert_eq!(emails, vec!["j.doe+wm@mail.example.org"]);
+        assert_eq!(phones, vec!["+1 (555) 010-0199"]);
+        assert!(find_emails("meet @ noon, or at user@localhost").is_empty());
+    }
+
+    #[test]
+    fn test_restore_partial_holds_split_placeholder() {
+        let mut redactor = redactor(&["Mara"], &[]);
+        redactor.redact("Mara");
+
+        let mut pending = String::new();
+        assert_eq!(redactor.restore_partial(&mut pending, "Then [PER"), "Then ");
+        assert_eq!(
+            redactor.restore_partial(&mut pending, "SON_1] sang"),
+            "Mara sang"
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_redactor_without_persist_leaves_placeholders_unsaved() {
+        let dir = std::env::temp_dir().join(format!("wm9000-privacy-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(PRIVACY_FILE);
+        let privacy = ProjectPrivacy {
+            settings: PrivacySettings {
+                enabled: true,
+                names: vec!["Mara".to_string()],
+                ..Default::default()
+            },
+            placeholders: BTreeMap::new(),
+        };
+        save_privacy(&path, &privacy).unwrap();
+
+        let mut preview = Redactor::load(&path, false).unwrap().unwrap();
+        assert_eq!(preview.redact("Mara sang"), "[PERSON_1] sang");
+        assert!(load_privacy(&path).unwrap().placeholders.is_empty());
+
+        let mut request = Redactor::load(&path, true).unwrap().unwrap();
+        request.redact("Mara sang");
+        assert_eq!(load_privacy(&path).unwrap().placeholders.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySettings {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_retries: 3,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+// Delay before retry number `attempt` (starting at 0). A provider's
+// `retry-after` wins, unless it asks us to wait longer than `max_delay_ms`,
+// in which case we give up. `jitter` in [0, 1] spreads the second half of
+// the exponential delay so parallel chunks do not retry in lockstep.
+pub fn backoff_delay(
+    settings: &RetrySettings,
+    attempt: u32,
+    retry_after_secs: Option<u64>,
+    jitter: f64,
+) -> Option<Duration> {
+    if let Some(secs) = retry_after_secs {
+        let ms = secs.saturating_mul(1000);
+        return (ms <= settings.max_delay_ms).then(|| Duration::from_millis(ms));
+    }
+
+    let exponential = settings
+        .base_delay_ms
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(settings.max_delay_ms);
+    let half = exponential / 2;
+    Some(Duration::from_millis(
+        half + (half as f64 * jitter.clamp(0.0, 1.0)) as u64,
+    ))
+}
+
+pub fn random_jitter() -> f64 {
+    rand::thread_rng().gen_range(0.0..=1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let settings = RetrySettings::default();
+        assert_eq!(
+            backoff_delay(&settings, 0, None, 1.0),
+            Some(Duration::from_millis(1000))
+        );
+        assert_eq!(
+            backoff_delay(&settings, 2, None, 0.0),
+            Some(Duration::from_millis(2000))
+        );
+        assert_eq!(
+            backoff_delay(&settings, 10, None, 1.0),
+            Some(Duration::from_millis(30_000))
+        );
+    }
+
+    #[test]
+    fn test_retry_after_is_honoured_within_limit() {
+        let settings = RetrySettings::default();
+        assert_eq!(
+            backoff_delay(&settings, 0, Some(5), 0.5),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(backoff_delay(&settings, 0, Some(120), 0.5), None);
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::ai::common::budget::{check_budget, crossed_warnings, BudgetSpend};
+use crate::ai::common::chunker::estimate_tokens;
+use crate::ai::common::PreparedPrompt;
+use crate::ai::error::AiError;
+use crate::ai::settings::{load_ai_settings, user_data_path};
+
+const USAGE_FILE: &str = "ai_usage.jsonl";
+const NO_PROJECT: &str = "(no project)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    // Streams report usage in pieces, some of them running totals, so keep
+    // the largest count seen for each side
+    pub fn absorb(&mut self, other: TokenUsage) {
+        self.prompt_tokens = self.prompt_tokens.max(other.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.max(other.completion_tokens);
+    }
+}
+
+// Price of a model in dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+// Used for models missing from the price table in the AI settings
+fn builtin_prices() -> HashMap<String, ModelPrice> {
+    [
+        ("gpt-3.5-turbo", 0.5, 1.5),
+        ("gpt-4o", 2.5, 10.0),
+        ("gpt-4o-mini", 0.15, 0.6),
+        ("claude-3-5-haiku", 0.8, 4.0),
+        ("claude-3-5-sonnet", 3.0, 15.0),
+    ]
+    .into_iter()
+    .map(|(model, input, output)| {
+        (
+            model.to_string(),
+            ModelPrice {
+                input_per_million: input,
+                output_per_million: output,
+            },
+        )
+    })
+    .collect()
+}
+
+// Configured prices win over built-in ones. Dated or `-latest` model names
+// match the longest priced prefix.
+pub fn price_for(prices: &HashMap<String, ModelPrice>, model: &str) -> Option<ModelPrice> {
+    let lookup = |table: &HashMap<String, ModelPrice>| {
+        table
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    };
+    lookup(prices).or_else(|| lookup(&builtin_prices()))
+}
+
+pub fn cost_of(price: ModelPrice, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    (prompt_tokens as f64 * price.input_per_million
+        + completion_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: String,
+    pub provider: String,
+    pub model: String,
+    pub command: String,
+    pub project: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    // The provider reported no usage, so the counts are our own estimate
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl UsageRecord {
+    fn day(&self) -> &str {
+        self.timestamp.get(..10).unwrap_or(&self.timestamp)
+    }
+}
+
+// Where a request's usage is recorded and who it is billed to
+#[derive(Clone)]
+pub struct UsageScope {
+    pub app: AppHandle,
+    pub ledger: PathBuf,
+    pub command: String,
+    pub project: Option<String>,
+    // Read from the ledger on the first check, then updated as requests
+    // finish; shared by every prompt of the job
+    spend: Arc<Mutex<Option<BudgetSpend>>>,
+}
+
+impl UsageScope {
+    pub fn new(app: &AppHandle, command: &str, project: Option<String>) -> Result<Self, String> {
+        Ok(UsageScope {
+            app: app.clone(),
+            ledger: user_data_path(app, USAGE_FILE)?,
+            command: command.to_string(),
+            project: project.filter(|p| !p.trim().is_empty()),
+            spend: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    fn spend(&self, prices: &HashMap<String, ModelPrice>) -> Result<BudgetSpend, String> {
+        let mut spend = self.spend.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(spend) = spend.as_ref() {
+            return Ok(spend.clone());
+        }
+        let records = read_records(&self.ledger)?;
+        let month = Local::now().format("%Y-%m").to_string();
+        let loaded = BudgetSpend::from_records(&records, prices, self.project.as_deref(), &month);
+        *spend = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    // Checked before every request, so a long job stops at the cap
+    pub fn check_budget(&self) -> Result<(), AiError> {
+        let settings = load_ai_settings(&self.app)?;
+        check_budget(&settings.budget, &self.spend(&settings.prices)?)
+    }
+}
+
+// The ledger is one JSON record per line, so recording is a cheap append
+pub fn append_record(path: &Path, record: &UsageRecord) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+
+    let line = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize usage record: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open usage ledger: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write usage ledger: {}", e))
+}
+
+pub fn read_records(path: &Path) -> Result<Vec<UsageRecord>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read usage ledger: {}", e))?;
+    // Skip lines cut short by a crash rather than losing the whole ledger
+    Ok(raw
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// Record what a finished request used. Falls back to an estimate when the
+// provider sent no usage, e.g. some local servers.
+pub fn record_usage(prompt: &PreparedPrompt, usage: Option<TokenUsage>, output: &str) {
+    let estimated = usage.is_none();
+    let usage = usage.unwrap_or_else(|| TokenUsage {
+        prompt_tokens: prompt
+            .messages
+            .iter()
+            .map(|m| estimate_tokens(&m.content) as u64)
+            .sum(),
+        completion_tokens: estimate_tokens(output) as u64,
+    });
+
+    let record = UsageRecord {
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        provider: prompt.provider.id().to_string(),
+        model: prompt.model.clone(),
+        command: prompt.scope.command.clone(),
+        project: prompt.scope.project.clone(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        estimated,
+    };
+
+    let scope = &prompt.scope;
+    if let Ok(settings) = load_ai_settings(&scope.app) {
+        if let Ok(before) = scope.spend(&settings.prices) {
+            for warning in crossed_warnings(&settings.budget, &settings.prices, &before, &record) {
+                let _ = scope.app.emit("ai-budget-warning", warning);
+            }
+        }
+        if let Some(spend) = scope
+            .spend
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            spend.add(&record, &settings.prices);
+        }
+    }
+
+    // A failed write must not cost the user the response they paid for
+    let _ = append_record(&scope.ledger, &record);
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord, cost: f64) {
+        self.requests += 1;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        self.cost += cost;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageBucket {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageSummary {
+    pub total: UsageTotals,
+    pub by_day: Vec<UsageBucket>,
+    pub by_project: Vec<UsageBucket>,
+    pub by_command: Vec<UsageBucket>,
+    // Models with no known price; their cost is counted as zero
+    pub unpriced_models: Vec<String>,
+}
+
+fn buckets(map: BTreeMap<String, UsageTotals>) -> Vec<UsageBucket> {
+    map.into_iter()
+        .map(|(key, totals)| UsageBucket { key, totals })
+        .collect()
+}
+
+// Totals for records between `from` and `to` (inclusive YYYY-MM-DD days)
+pub fn summarize(
+    records: &[UsageRecord],
+    prices: &HashMap<String, ModelPrice>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> UsageSummary {
+    let mut summary = UsageSummary::default();
+    let mut by_day: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut by_project: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut by_command: BTreeMap<String, UsageTotals> = BTreeMap::new();
+
+    for record in records {
+        let day = record.day();
+        if from.is_some_and(|from| day < from) || to.is_some_and(|to| day > to) {
+            continue;
+        }
+
+        let cost = match price_for(prices, &record.model) {
+            Some(price) => cost_of(price, record.prompt_tokens, record.completion_tokens),
+            None => {
+                if !summary.unpriced_models.contains(&record.model) {
+                    summary.unpriced_models.push(record.model.clone());
+                }
+                0.0
+            }
+        };
+
+        summary.total.add(record, cost);
+        by_day.entry(day.to_string()).or_default().add(record, cost);
+        by_project
+            .entry(
+                record
+                    .project
+                    .clone()
+                    .unwrap_or_else(|| NO_PROJECT.to_string()),
+            )
+            .or_default()
+            .add(record, cost);
+        by_command
+            .entry(record.command.clone())
+            .or_default()
+            .add(record, cost);
+    }
+
+    summary.by_day = buckets(by_day);
+    summary.by_project = buckets(by_project);
+    summary.by_command = buckets(by_command);
+    summary
+}
+
+#[tauri::command]
+pub fn get_ai_usage(
+    app: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<UsageSummary, String> {
+    let settings = load_ai_settings(&app)?;
+    let records = read_records(&user_data_path(&app, USAGE_FILE)?)?;
+    Ok(summarize(
+        &records,
+        &settings.prices,
+        from.as_deref(),
+        to.as_deref(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: &str, model: &str, command: &str, project: Option<&str>) -> UsageRecord {
+        UsageRecord {
+            timestamp: timestamp.to_string(),
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            command: command.to_string(),
+            project: project.map(|p| p.to_string()),
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_price_lookup_prefers_settings_and_longest_prefix() {
+        let mut prices = HashMap::new();
+        assert_eq!(
+            price_for(&prices, "gpt-4o-mini-2024-07-18")
+                .unwrap()
+                .input_per_million,
+            0.15
+        );
+
+        prices.insert(
+            "gpt-4o-mini".to_string(),
+            ModelPrice {
+                input_per_million: 1.0,
+                output_per_million: 2.0,
+            },
+        );
+        assert_eq!(
+            price_for(&prices, "gpt-4o-mini").unwrap().input_per_million,
+            1.0
+        );
+        assert!(price_for(&prices, "llama3.1").is_none());
+    }
+
+    #[test]
+    fn test_summarize_groups_and_filters() {
+        let records = vec![
+            record(
+                "2026-03-01 10:00:00",
+                "gpt-3.5-turbo",
+                "review",
+                Some("Novel"),
+            ),
+            record("2026-03-01 11:00:00", "gpt-3.5-turbo", "proofread", None),
+            record("2026-03-02 09:00:00", "llama3.1", "review", Some("Novel")),
+            record(
+                "2026-02-28 09:00:00",
+                "gpt-3.5-turbo",
+                "review",
+                Some("Novel"),
+            ),
+        ];
+        let summary = summarize(&records, &HashMap::new(), Some("2026-03-01"), None);
+
+        assert_eq!(summary.total.requests, 3);
+        // 1M prompt tokens at $0.50 plus 0.5M completion tokens at $1.50
+        assert!((summary.total.cost - 2.5).abs() < 1e-9);
+        assert_eq!(summary.by_day.len(), 2);
+        assert_eq!(summary.by_day[0].key, "2026-03-01");
+        assert_eq!(summary.by_day[0].totals.requests, 2);
+        assert_eq!(summary.by_project[0].key, NO_PROJECT);
+        assert_eq!(summary.by_project[1].totals.requests, 2);
+        assert_eq!(summary.by_command.len(), 2);
+        assert_eq!(summary.unpriced_models, vec!["llama3.1".to_string()]);
+    }
+
+    #[test]
+    fn test_stream_usage_keeps_running_totals() {
+        let mut usage = TokenUsage::default();
+        usage.absorb(TokenUsage {
+            prompt_tokens: 120,
+            completion_tokens: 1,
+        });
+        usage.absorb(TokenUsage {
+            prompt_tokens: 0,
+            completion_tokens: 45,
+        });
+        assert_eq!(
+            usage,
+            TokenUsage {
+                prompt_tokens: 120,
+                completion_tokens: 45
+            }
+        );
+    }
+}
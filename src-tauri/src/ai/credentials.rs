@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::ai::common::{create_client, create_provider_with_key, ChatMessage, CompletionRequest};
+use crate::ai::error::AiError;
+use crate::ai::settings::{load_ai_settings, AiSettings, ModelParams, ProviderKind};
+
+const CREDENTIALS_FILE: &str = "credentials.json";
+// Encrypted alongside the keys so a wrong passphrase can be told apart
+const CHECK_VALUE: &str = "WordsMaker9000";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CredentialFile {
+    // Argon2 salt, base64
+    salt: String,
+    // Keys are encrypted with a user passphrase rather than a key derived
+    // from this machine's id
+    passphrase: bool,
+    check: Sealed,
+    // By provider, as named in the AI settings
+    keys: BTreeMap<String, Sealed>,
+}
+
+// The decryption key for the credentials file, kept in memory once derived
+// so a passphrase is asked for once per session
+#[derive(Default)]
+pub struct CredentialKeys {
+    unlocked: Mutex<Option<[u8; 32]>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialStatus {
+    pub providers: Vec<String>,
+    pub passphrase: bool,
+    pub locked: bool,
+}
+
+fn provider_name(provider: ProviderKind) -> &'static str {
+    match provider {
+        ProviderKind::OpenAi => "openai",
+        ProviderKind::Anthropic => "anthropic",
+        ProviderKind::Ollama => "ollama",
+    }
+}
+
+fn derive_key(secret: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+    Ok(key)
+}
+
+// Not secret from someone with access to this machine, but keeps keys out
+// of backups and synced folders in plain text
+fn machine_secret() -> Result<String, String> {
+    machine_uid::get().map_err(|e| format!("Failed to read machine id: {}", e))
+}
+
+fn seal(key: &[u8; 32], plaintext: &str) -> Result<Sealed, String> {
+    let cipher = Aes256Gcm::new(key.into());
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "Failed to encrypt credentials".to_string())?;
+    Ok(Sealed {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; 32], sealed: &Sealed) -> Result<String, String> {
+    let nonce = BASE64
+        .decode(&sealed.nonce)
+        .map_err(|e| format!("Corrupt credentials file: {}", e))?;
+    let ciphertext = BASE64
+        .decode(&sealed.ciphertext)
+        .map_err(|e| format!("Corrupt credentials file: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("Corrupt credentials file: bad nonce".to_string());
+    }
+
+    let plaintext = Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase, or the credentials file is corrupt.".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| format!("Corrupt credentials file: {}", e))
+}
+
+impl CredentialFile {
+    fn new(secret: &[u8], passphrase: bool) -> Result<(Self, [u8; 32]), String> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = derive_key(secret, &salt)?;
+
+        let file = CredentialFile {
+            salt: BASE64.encode(salt),
+            passphrase,
+            check: seal(&key, CHECK_VALUE)?,
+            keys: BTreeMap::new(),
+        };
+        Ok((file, key))
+    }
+
+    fn unlock(&self, secret: &[u8]) -> Result<[u8; 32], String> {
+        let salt = BASE64
+            .decode(&self.salt)
+            .map_err(|e| format!("Corrupt credentials file: {}", e))?;
+        let key = derive_key(secret, &salt)?;
+        open(&key, &self.check)?;
+        Ok(key)
+    }
+}
+
+fn credentials_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config dir: {}", e))?;
+    Ok(config_dir.join(CREDENTIALS_FILE))
+}
+
+fn load_credentials(app: &AppHandle) -> Result<Option<CredentialFile>, String> {
+    let path = credentials_path(app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let raw =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read credentials: {}", e))?;
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse credentials: {}", e))
+}
+
+fn save_credentials(app: &AppHandle, file: &CredentialFile) -> Result<(), String> {
+    let path = credentials_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let raw = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+    fs::write(&path, raw).map_err(|e| format!("Failed to write credentials: {}", e))
+}
+
+fn cached_key(app: &AppHandle) -> Option<[u8; 32]> {
+    let keys = app.try_state::<CredentialKeys>()?;
+    let unlocked = keys.unlocked.lock().ok()?;
+    *unlocked
+}
+
+fn cache_key(app: &AppHandle, key: Option<[u8; 32]>) {
+    if let Some(keys) = app.try_state::<CredentialKeys>() {
+        if let Ok(mut unlocked) = keys.unlocked.lock() {
+            *unlocked = key;
+        }
+    }
+}
+
+// The key for an existing file: cached, else from the given passphrase or
+// this machine's id
+fn file_key(
+    app: &AppHandle,
+    file: &CredentialFile,
+    passphrase: Option<&str>,
+) -> Result<[u8; 32], AiError> {
+    if let Some(passphrase) = passphrase {
+        let key = file.unlock(passphrase.as_bytes())?;
+        cache_key(app, Some(key));
+        return Ok(key);
+    }
+    if let Some(key) = cached_key(app) {
+        return Ok(key);
+    }
+    if file.passphrase {
+        return Err(AiError::CredentialsLocked);
+    }
+
+    let key = file.unlock(machine_secret()?.as_bytes())?;
+    cache_key(app, Some(key));
+    Ok(key)
+}
+
+// The saved key for a provider, if one was saved
+pub fn stored_api_key(app: &AppHandle, provider: ProviderKind) -> Result<Option<String>, AiError> {
+    let file = match load_credentials(app)? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let sealed = match file.keys.get(provider_name(provider)) {
+        Some(sealed) => sealed,
+        None => return Ok(None),
+    };
+
+    let key = file_key(app, &file, None)?;
+    Ok(Some(open(&key, sealed)?))
+}
+
+#[tauri::command]
+pub fn get_credential_status(app: AppHandle) -> Result<CredentialStatus, String> {
+    Ok(match load_credentials(&app)? {
+        Some(file) => CredentialStatus {
+            providers: file.keys.keys().cloned().collect(),
+            passphrase: file.passphrase,
+            locked: file.passphrase && cached_key(&app).is_none(),
+        },
+        None => CredentialStatus {
+            providers: Vec::new(),
+            passphrase: false,
+            locked: false,
+        },
+    })
+}
+
+// Save a provider's key. The first key saved decides whether the file is
+// protected by `passphrase` or by this machine; after that a passphrase is
+// only needed if the keys are locked.
+#[tauri::command]
+pub fn set_api_key(
+    app: AppHandle,
+    provider: ProviderKind,
+    api_key: String,
+    passphrase: Option<String>,
+) -> Result<(), AiError> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("The API key is empty.".to_string().into());
+    }
+
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    let (mut file, key) = match load_credentials(&app)? {
+        Some(file) => {
+            let key = file_key(&app, &file, passphrase.as_deref())?;
+            (file, key)
+        }
+        None => {
+            let (file, key) = match &passphrase {
+                Some(passphrase) => CredentialFile::new(passphrase.as_bytes(), true)?,
+                None => CredentialFile::new(machine_secret()?.as_bytes(), false)?,
+            };
+            cache_key(&app, Some(key));
+            (file, key)
+        }
+    };
+
+    file.keys
+        .insert(provider_name(provider).to_string(), seal(&key, api_key)?);
+    Ok(save_credentials(&app, &file)?)
+}
+
+#[tauri::command]
+pub fn unlock_api_keys(app: AppHandle, passphrase: String) -> Result<(), AiError> {
+    let file = load_credentials(&app)?.ok_or("No API keys have been saved.".to_string())?;
+    file_key(&app, &file, Some(&passphrase))?;
+    Ok(())
+}
+
+// Remove one provider's key, or every saved key (and the passphrase) if no
+// provider is given
+#[tauri::command]
+pub fn clear_api_key(app: AppHandle, provider: Option<ProviderKind>) -> Result<(), String> {
+    let mut file = match load_credentials(&app)? {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+
+    if let Some(provider) = provider {
+        file.keys.remove(provider_name(provider));
+        if !file.keys.is_empty() {
+            return save_credentials(&app, &file);
+        }
+    }
+
+    cache_key(&app, None);
+    fs::remove_file(credentials_path(&app)?)
+        .map_err(|e| format!("Failed to remove credentials: {}", e))
+}
+
+// Check a key against the provider with a one-token request. Tests the
+// given key, or the one in use (saved or from the environment).
+#[tauri::command]
+// Point the settings at `provider` and return the parameters for a one-token
+// ping. The configured endpoint and model belong to the configured provider,
+// so testing another provider's key uses that provider's defaults.
+fn ping_params(settings: &mut AiSettings, provider: ProviderKind) -> ModelParams {
+    let mut params = ModelParams {
+        max_tokens: Some(1),
+        timeout_secs: Some(30),
+        ..settings.defaults.clone()
+    };
+    if settings.provider != provider {
+        settings.provider = provider;
+        settings.base_url = None;
+        params.model = None;
+    }
+    params
+}
+
+pub async fn test_api_key(
+    app: AppHandle,
+    provider: ProviderKind,
+    api_key: Option<String>,
+) -> Result<String, AiError> {
+    let mut settings = load_ai_settings(&app)?;
+    let params = ping_params(&mut settings, provider);
+    let provider = create_provider_with_key(&app, &settings, api_key)?;
+
+    let model = params
+        .model
+        .clone()
+        .unwrap_or_else(|| provider.default_model().to_string());
+    let messages = [ChatMessage::user("ping")];
+    let request = provider.build_request(
+        &create_client(),
+        &CompletionRequest {
+            model: &model,
+            params: &params,
+            messages: &messages,
+            stream: false,
+        },
+    );
+
+    let response = request.send().await.map_err(AiError::from_reqwest)?;
+    let status = response.status().as_u16();
+    if response.status().is_success() {
+        return Ok(format!("Connected to {} using {}.", provider.id(), model));
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(AiError::from_status(status, None, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = derive_key(b"correct horse", b"0123456789abcdef").unwrap();
+        let sealed = seal(&key, "sk-test").unwrap();
+        assert_ne!(sealed.ciphertext, "sk-test");
+        assert_eq!(open(&key, &sealed).unwrap(), "sk-test");
+
+        // Every seal uses a fresh nonce
+        assert_ne!(seal(&key, "sk-test").unwrap(), sealed);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_rejected() {
+        let (file, key) = CredentialFile::new(b"correct horse", true).unwrap();
+        assert_eq!(file.unlock(b"correct horse").unwrap(), key);
+        assert!(file.unlock(b"battery staple").is_err());
+    }
+
+    #[test]
+    fn test_ping_uses_default_model_for_another_provider() {
+        let mut settings = AiSettings {
+            base_url: Some("http://localhost:8080/v1".to_string()),
+            defaults: ModelParams {
+                model: Some("gpt-4o".to_string()),
+                temperature: Some(0.2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let params = ping_params(&mut settings.clone(), ProviderKind::OpenAi);
+        assert_eq!(params.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(params.max_tokens, Some(1));
+
+        let params = ping_params(&mut settings, ProviderKind::Anthropic);
+        assert_eq!(params.model, None);
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(settings.provider, ProviderKind::Anthropic);
+        assert_eq!(settings.base_url, None);
+    }
+}
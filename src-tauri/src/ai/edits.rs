@@ -0,0 +1,279 @@
+use ego_tree::NodeRef;
+use scraper::node::Node;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::common::strip_code_fence;
+
+pub const STRUCTURED_PROOFREAD_ROLE: &str = "You are a professional editor. Proofread the text below. \
+Do not rewrite it. Respond with JSON only, in the form \
+{\"edits\": [{\"original\": \"...\", \"replacement\": \"...\", \"category\": \"spelling|grammar|punctuation|style\", \"explanation\": \"...\"}]}. \
+\"original\" must be copied exactly from the text and be just long enough to be unique. \
+List edits in the order they appear. Return {\"edits\": []} if nothing needs fixing.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditCategory {
+    Spelling,
+    Grammar,
+    Punctuation,
+    Style,
+}
+
+impl EditCategory {
+    fn parse(raw: &str) -> Option<EditCategory> {
+        match raw.trim().to_lowercase().as_str() {
+            "spelling" => Some(EditCategory::Spelling),
+            "grammar" => Some(EditCategory::Grammar),
+            "punctuation" => Some(EditCategory::Punctuation),
+            "style" => Some(EditCategory::Style),
+            _ => None,
+        }
+    }
+}
+
+// An edit as the model returns it, before validation
+#[derive(Debug, Deserialize)]
+struct RawEdit {
+    #[serde(default)]
+    original: String,
+    #[serde(default)]
+    replacement: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    explanation: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawEdits {
+    edits: Vec<RawEdit>,
+}
+
+// A validated correction. `start` and `end` count UTF-16 code units in
+// `ProofreadEdits.text`, matching JavaScript string and Quill indices.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofreadEdit {
+    pub id: usize,
+    pub start: usize,
+    pub end: usize,
+    pub original: String,
+    pub replacement: String,
+    pub category: EditCategory,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofreadEdits {
+    pub text: String,
+    pub edits: Vec<ProofreadEdit>,
+    // Edits dropped because they were malformed or did not match the text
+    pub rejected: usize,
+    pub cached: bool,
+}
+
+// Flatten Quill HTML into the plain text the model sees, one line per block
+pub fn html_to_plain_text(html: &str) -> String {
+    let document = Html::parse_fragment(html);
+    let mut text = String::new();
+    collect_text(&document.root_element(), &mut text);
+    text.trim_end_matches('\n').to_string()
+}
+
+fn collect_text(node: &NodeRef<'_, Node>, out: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&text.text),
+            Node::Element(el) => match el.name() {
+                "br" => out.push('\n'),
+                "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li" | "blockquote" | "pre"
+                | "div" => {
+                    collect_text(&child, out);
+                    if !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+                _ => collect_text(&child, out),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+// Validate the model's edit list and anchor each edit in `text`
+pub fn parse_edits(raw: &str, text: &str) -> Result<ProofreadEdits, String> {
+    let parsed: RawEdits = serde_json::from_str(strip_code_fence(raw))
+        .map_err(|e| format!("Proofreader did not return a valid edit list: {}", e))?;
+
+    let mut edits: Vec<ProofreadEdit> = Vec::new();
+    let mut rejected = 0;
+    // Byte offset after the last accepted edit; edits arrive in text order
+    let mut cursor = 0;
+
+    for raw_edit in parsed.edits {
+        let category = match EditCategory::parse(&raw_edit.category) {
+            Some(category) => category,
+            None => {
+                rejected += 1;
+                continue;
+            }
+        };
+        if raw_edit.original.is_empty() || raw_edit.original == raw_edit.replacement {
+            rejected += 1;
+            continue;
+        }
+
+        let found = text[cursor..]
+            .find(&raw_edit.original)
+            .map(|pos| pos + cursor)
+            .or_else(|| {
+                // Out of order: accept an earlier match only if it does not
+                // overlap an edit we already placed
+                text.find(&raw_edit.original).filter(|&pos| {
+                    let end = pos + raw_edit.original.len();
+                    !edits.iter().any(|e| overlaps(text, e, pos, end))
+                })
+            });
+
+        let byte_start = match found {
+            Some(pos) => pos,
+            None => {
+                rejected += 1;
+                continue;
+            }
+        };
+        let byte_end = byte_start + raw_edit.original.len();
+        cursor = cursor.max(byte_end);
+
+        let start = utf16_len(&text[..byte_start]);
+        edits.push(ProofreadEdit {
+            id: 0,
+            start,
+            end: start + utf16_len(&raw_edit.original),
+            original: raw_edit.original,
+            replacement: raw_edit.replacement,
+            category,
+            explanation: raw_edit.explanation,
+        });
+    }
+
+    edits.sort_by_key(|e| e.start);
+    for (i, edit) in edits.iter_mut().enumerate() {
+        edit.id = i;
+    }
+
+    Ok(ProofreadEdits {
+        text: text.to_string(),
+        edits,
+        rejected,
+        cached: false,
+    })
+}
+
+// Combine per-chunk results. Each part's offsets are relative to the chunk
+// that starts at the given byte offset of `text`.
+pub fn merge_edits(text: &str, parts: Vec<(usize, ProofreadEdits)>) -> ProofreadEdits {
+    let mut edits: Vec<ProofreadEdit> = Vec::new();
+    let mut rejected = 0;
+
+    for (offset, part) in parts {
+        let shift = utf16_len(&text[..offset]);
+        rejected += part.rejected;
+        edits.extend(part.edits.into_iter().map(|mut edit| {
+            edit.start += shift;
+            edit.end += shift;
+            edit
+        }));
+    }
+
+    for (i, edit) in edits.iter_mut().enumerate() {
+        edit.id = i;
+    }
+
+    ProofreadEdits {
+        text: text.to_string(),
+        edits,
+        rejected,
+        cached: false,
+    }
+}
+
+fn overlaps(text: &str, edit: &ProofreadEdit, byte_start: usize, byte_end: usize) -> bool {
+    let start = utf16_len(&text[..byte_start]);
+    let end = start + utf16_len(&text[byte_start..byte_end]);
+    start < edit.end && edit.start < end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text_from_quill_html() {
+        let text = html_to_plain_text("<p>Hello <em>world</em></p><p>Line<br>break</p>");
+        assert_eq!(text, "Hello world\nLine\nbreak");
+    }
+
+    #[test]
+    fn test_edits_anchored_in_order() {
+        let text = "Teh cat sat. Teh dog ran.";
+        let raw = r#"```json
+        {"edits": [
+            {"original": "Teh", "replacement": "The", "category": "spelling", "explanation": "Typo"},
+            {"original": "Teh dog", "replacement": "The dog", "category": "Spelling", "explanation": "Typo"}
+        ]}
+        ```"#;
+        let result = parse_edits(raw, text).unwrap();
+        assert_eq!(result.rejected, 0);
+        assert_eq!(result.edits.len(), 2);
+        assert_eq!((result.edits[0].start, result.edits[0].end), (0, 3));
+        assert_eq!((result.edits[1].start, result.edits[1].end), (13, 20));
+        assert_eq!(result.edits[1].category, EditCategory::Spelling);
+    }
+
+    #[test]
+    fn test_invalid_edits_rejected() {
+        let text = "A fine sentence.";
+        let raw = r#"{"edits": [
+            {"original": "missing", "replacement": "x", "category": "grammar"},
+            {"original": "fine", "replacement": "good", "category": "tone"},
+            {"original": "fine", "replacement": "fine", "category": "style"},
+            {"original": "sentence", "replacement": "line", "category": "style"}
+        ]}"#;
+        let result = parse_edits(raw, text).unwrap();
+        assert_eq!(result.rejected, 3);
+        assert_eq!(result.edits.len(), 1);
+        assert_eq!(result.edits[0].original, "sentence");
+    }
+
+    #[test]
+    fn test_offsets_count_utf16_units() {
+        let text = "🙂 recieve";
+        let raw = r#"{"edits": [{"original": "recieve", "replacement": "receive", "category": "spelling"}]}"#;
+        let result = parse_edits(raw, text).unwrap();
+        assert_eq!(result.edits[0].start, 3);
+        assert_eq!(result.edits[0].end, 10);
+    }
+
+    #[test]
+    fn test_merge_edits_shifts_offsets() {
+        let text = "First part.\nSecnd part.";
+        let second = parse_edits(
+            r#"{"edits": [{"original": "Secnd", "replacement": "Second", "category": "spelling"}]}"#,
+            "Secnd part.",
+        )
+        .unwrap();
+        let merged = merge_edits(text, vec![(12, second)]);
+        assert_eq!(merged.text, text);
+        assert_eq!((merged.edits[0].start, merged.edits[0].end), (12, 17));
+    }
+
+    #[test]
+    fn test_not_json_is_error() {
+        assert!(parse_edits("Looks good to me!", "text").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AiError {
+    MissingKey {
+        provider: String,
+    },
+    Auth {
+        message: String,
+    },
+    RateLimited {
+        retry_after_secs: Option<u64>,
+        message: String,
+    },
+    ContextTooLong {
+        message: String,
+    },
+    Timeout,
+    Network {
+        message: String,
+    },
+    MalformedResponse {
+        message: String,
+    },
+    // Any other non-success status from the provider
+    Provider {
+        status: u16,
+        message: String,
+    },
+    // Saved API keys are protected by a passphrase not yet entered
+    CredentialsLocked,
+    // A configured spending cap is used up; `budget` is "monthly" or "project"
+    BudgetExceeded {
+        budget: String,
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl AiError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AiError::MissingKey { .. } => "missing_key",
+            AiError::Auth { .. } => "auth",
+            AiError::RateLimited { .. } => "rate_limited",
+            AiError::ContextTooLong { .. } => "context_too_long",
+            AiError::Timeout => "timeout",
+            AiError::Network { .. } => "network",
+            AiError::MalformedResponse { .. } => "malformed_response",
+            AiError::Provider { .. } => "provider",
+            AiError::CredentialsLocked => "credentials_locked",
+            AiError::BudgetExceeded { .. } => "budget_exceeded",
+            AiError::Other { .. } => "other",
+        }
+    }
+
+    // Whether the same request may succeed if sent again later
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AiError::RateLimited { .. } | AiError::Timeout | AiError::Network { .. } => true,
+            // 529 is Anthropic's "overloaded"
+            AiError::Provider { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            AiError::RateLimited {
+                retry_after_secs, ..
+            } => *retry_after_secs,
+            _ => None,
+        }
+    }
+
+    pub fn malformed(message: impl Into<String>) -> Self {
+        AiError::MalformedResponse {
+            message: message.into(),
+        }
+    }
+
+    pub fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AiError::Timeout
+        } else if e.is_decode() {
+            AiError::malformed(e.to_string())
+        } else {
+            AiError::Network {
+                message: e.to_string(),
+            }
+        }
+    }
+
+    // Classify a non-success HTTP response from a provider
+    pub fn from_status(status: u16, retry_after_secs: Option<u64>, body: &str) -> Self {
+        let message = provider_error_message(body);
+        let lower = message.to_lowercase();
+
+        match status {
+            401 | 403 => AiError::Auth { message },
+            429 if lower.contains("quota") => AiError::Provider { status, message },
+            429 => AiError::RateLimited {
+                retry_after_secs,
+                message,
+            },
+            400 | 413
+                if lower.contains("context length")
+                    || lower.contains("context_length")
+                    || lower.contains("too long")
+                    || lower.contains("too many tokens") =>
+            {
+                AiError::ContextTooLong { message }
+            }
+            _ => AiError::Provider { status, message },
+        }
+    }
+}
+
+// Pull the human-readable message out of an OpenAI, Anthropic or Ollama
+// error body, falling back to the raw body
+fn provider_error_message(body: &str) -> String {
+    let json: serde_json::Value = match serde_json::from_str(body) {
+        Ok(json) => json,
+        Err(_) => return body.trim().to_string(),
+    };
+
+    json["error"]["message"]
+        .as_str()
+        .or_else(|| json["error"].as_str())
+        .or_else(|| json["message"].as_str())
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiError::MissingKey { provider } => {
+                write!(f, "No API key is configured for {}.", provider)
+            }
+            AiError::Auth { message } => {
+                write!(f, "The AI provider rejected the API key: {}", message)
+            }
+            AiError::RateLimited {
+                retry_after_secs: Some(secs),
+                message,
+            } => write!(
+                f,
+                "Rate limited by the AI provider; try again in {}s. {}",
+                secs, message
+            ),
+            AiError::RateLimited { message, .. } => {
+                write!(f, "Rate limited by the AI provider. {}", message)
+            }
+            AiError::ContextTooLong { message } => write!(
+                f,
+                "The content is too long for the model's context window. \
+                 Lower the chunk size in the AI settings. {}",
+                message
+            ),
+            AiError::Timeout => write!(f, "The AI request timed out."),
+            AiError::Network { message } => {
+                write!(f, "Could not reach the AI provider: {}", message)
+            }
+            AiError::MalformedResponse { message } => {
+                write!(
+                    f,
+                    "The AI provider returned an unexpected response: {}",
+                    message
+                )
+            }
+            AiError::Provider { status, message } => {
+                write!(
+                    f,
+                    "The AI provider returned an error ({}): {}",
+                    status, message
+                )
+            }
+            AiError::CredentialsLocked => write!(
+                f,
+                "Your saved API keys are locked. Enter your passphrase to unlock them."
+            ),
+            AiError::BudgetExceeded { message, .. } => write!(
+                f,
+                "AI budget exceeded. {} Raise the limit in the AI settings to continue.",
+                message
+            ),
+            AiError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+// Settings and file errors elsewhere in the app are plain strings
+impl From<String> for AiError {
+    fn from(message: String) -> Self {
+        AiError::Other { message }
+    }
+}
+
+// Sent to the frontend as { kind, message, retry_after_secs }
+impl Serialize for AiError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AiError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retry_after_secs", &self.retry_after_secs())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_statuses() {
+        let body = r#"{"error": {"message": "Incorrect API key provided"}}"#;
+        assert_eq!(
+            AiError::from_status(401, None, body),
+            AiError::Auth {
+                message: "Incorrect API key provided".to_string()
+            }
+        );
+
+        let limited = AiError::from_status(429, Some(7), r#"{"error": "slow down"}"#);
+        assert_eq!(limited.retry_after_secs(), Some(7));
+        assert!(limited.is_transient());
+
+        let quota = AiError::from_status(
+            429,
+            None,
+            r#"{"error": {"message": "You exceeded your current quota"}}"#,
+        );
+        assert!(!quota.is_transient());
+
+        let context = AiError::from_status(
+            400,
+            None,
+            r#"{"error": {"message": "This model's maximum context length is 16385 tokens"}}"#,
+        );
+        assert_eq!(context.kind(), "context_too_long");
+
+        assert!(AiError::from_status(529, None, "Overloaded").is_transient());
+        assert!(!AiError::from_status(404, None, "Not found").is_transient());
+    }
+
+    #[test]
+    fn test_serializes_kind_and_message() {
+        let json = serde_json::to_value(AiError::Timeout).unwrap();
+        assert_eq!(json["kind"], "timeout");
+        assert_eq!(json["message"], "The AI request timed out.");
+        assert!(json["retry_after_secs"].is_null());
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::ai::error::AiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiJobStatus {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+// Sent on the "ai-job" event when a job finishes, fails or is cancelled
+#[derive(Debug, Clone, Serialize)]
+pub struct AiJobEvent {
+    pub job_id: String,
+    pub command: String,
+    pub status: AiJobStatus,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<AiError>,
+}
+
+struct RunningJob {
+    command: String,
+    handle: JoinHandle<()>,
+}
+
+// AI requests in flight, managed as Tauri state so they can be cancelled
+#[derive(Default)]
+pub struct AiJobs {
+    running: Mutex<HashMap<String, RunningJob>>,
+}
+
+impl AiJobs {
+    fn finish(&self, job_id: &str) -> Option<RunningJob> {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(job_id)
+    }
+}
+
+fn emit_job_event(
+    app: &AppHandle,
+    job_id: &str,
+    command: &str,
+    status: AiJobStatus,
+    result: Option<serde_json::Value>,
+    error: Option<AiError>,
+) {
+    let _ = app.emit(
+        "ai-job",
+        AiJobEvent {
+            job_id: job_id.to_string(),
+            command: command.to_string(),
+            status,
+            result,
+            error,
+        },
+    );
+}
+
+// Run an AI command in the background and return its job id straight away.
+// The result arrives on the "ai-job" event.
+pub fn spawn_job<T, F>(app: &AppHandle, command: &str, task: F) -> String
+where
+    T: Serialize,
+    F: Future<Output = Result<T, AiError>> + Send + 'static,
+{
+    let jobs = app.state::<AiJobs>();
+    let mut running = jobs.running.lock().unwrap_or_else(|e| e.into_inner());
+    start_job(
+        app,
+        &mut running,
+        uuid::Uuid::new_v4().to_string(),
+        command,
+        task,
+    )
+}
+
+// As `spawn_job`, for commands whose caller already picked an id, such as
+// the request id of a streaming command. Fails if a job with that id is
+// still running, since its events and cancellation would be ambiguous.
+pub fn spawn_job_with_id<T, F>(
+    app: &AppHandle,
+    job_id: String,
+    command: &str,
+    task: F,
+) -> Result<String, AiError>
+where
+    T: Serialize,
+    F: Future<Output = Result<T, AiError>> + Send + 'static,
+{
+    let jobs = app.state::<AiJobs>();
+    let mut running = jobs.running.lock().unwrap_or_else(|e| e.into_inner());
+    if running.contains_key(&job_id) {
+        return Err(format!("An AI request with id {} is already running", job_id).into());
+    }
+    Ok(start_job(app, &mut running, job_id, command, task))
+}
+
+// Spawn the task and record it under `job_id`. The caller holds the lock
+// until the handle is stored, so a job that finishes immediately cannot try
+// to remove itself first.
+fn start_job<T, F>(
+    app: &AppHandle,
+    running: &mut HashMap<String, RunningJob>,
+    job_id: String,
+    command: &str,
+    task: F,
+) -> String
+where
+    T: Serialize,
+    F: Future<Output = Result<T, AiError>> + Send + 'static,
+{
+    let task_app = app.clone();
+    let task_id = job_id.clone();
+    let task_command = command.to_string();
+    let handle = async_runtime::spawn(async move {
+        let outcome = task.await.and_then(|value| {
+            serde_json::to_value(value)
+                .map_err(|e| format!("Failed to serialize AI result: {}", e).into())
+        });
+
+        // A cancelled job has already been removed and reported
+        if task_app.state::<AiJobs>().finish(&task_id).is_none() {
+            return;
+        }
+        match outcome {
+            Ok(result) => emit_job_event(
+                &task_app,
+                &task_id,
+                &task_command,
+                AiJobStatus::Completed,
+                Some(result),
+                None,
+            ),
+            Err(error) => emit_job_event(
+                &task_app,
+                &task_id,
+                &task_command,
+                AiJobStatus::Failed,
+                None,
+                Some(error),
+            ),
+        }
+    });
+
+    running.insert(
+        job_id.clone(),
+        RunningJob {
+            command: command.to_string(),
+            handle,
+        },
+    );
+    job_id
+}
+
+// Returns false if the job had already finished
+#[tauri::command]
+pub fn cancel_ai_job(app: AppHandle, job_id: String) -> bool {
+    let job = match app.state::<AiJobs>().finish(&job_id) {
+        Some(job) => job,
+        None => return false,
+    };
+
+    job.handle.abort();
+    emit_job_event(
+        &app,
+        &job_id,
+        &job.command,
+        AiJobStatus::Cancelled,
+        None,
+        None,
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_event_serializes_status() {
+        let event = AiJobEvent {
+            job_id: "job-1".to_string(),
+            command: "ai_review".to_string(),
+            status: AiJobStatus::Failed,
+            result: None,
+            error: Some(AiError::Timeout),
+        };
+        let json = serde_json::to_value(event).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["error"]["kind"], "timeout");
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::node::{Node, Text};
+use scraper::Html;
+
+use crate::ai::common::strip_code_fence;
+
+pub const MARKUP_PROOFREAD_ROLE: &str = "You are a professional editor. Proofread the text below. \
+Each piece of text starts with a numbered marker such as [[3]]. Correct spelling, grammar and \
+punctuation within each piece, but do not move words from one piece to another. Keep every \
+marker exactly as given, in the same order, and do not add or remove any. \
+Respond with the corrected text only.";
+
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "blockquote",
+    "div",
+];
+// Never proofread
+const SKIPPED_TAGS: &[&str] = &["pre", "code", "script", "style"];
+
+// A text node sent to the model, in document order
+struct Segment {
+    id: NodeId,
+    text: String,
+    // First text of a new block or line
+    new_line: bool,
+}
+
+fn collect_segments(node: &NodeRef<'_, Node>, segments: &mut Vec<Segment>, new_line: &mut bool) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) if !text.text.trim().is_empty() => {
+                segments.push(Segment {
+                    id: child.id(),
+                    text: text.text.to_string(),
+                    new_line: std::mem::take(new_line),
+                });
+            }
+            Node::Element(el) => match el.name() {
+                "br" => *new_line = true,
+                name if SKIPPED_TAGS.contains(&name) => *new_line = true,
+                name if BLOCK_TAGS.contains(&name) => {
+                    *new_line = true;
+                    collect_segments(&child, segments, new_line);
+                    *new_line = true;
+                }
+                _ => collect_segments(&child, segments, new_line),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn segments_of(document: &Html) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    collect_segments(&document.root_element(), &mut segments, &mut false);
+    segments
+}
+
+// Whitespace collapses in HTML, so a newline inside a text node reads as a
+// space and would only confuse line-based chunking
+fn sent_text(segment: &Segment) -> String {
+    segment.text.replace(['\r', '\n'], " ")
+}
+
+// The text runs of Quill HTML, each behind a [[n]] marker, one line per
+// block. Inline tags are left out, so the model cannot drop or move them.
+pub fn mark_segments(html: &str) -> String {
+    let document = Html::parse_fragment(html);
+    let mut marked = String::new();
+    for (i, segment) in segments_of(&document).iter().enumerate() {
+        if segment.new_line && !marked.is_empty() {
+            marked.push('\n');
+        }
+        marked.push_str(&format!("[[{}]]{}", i + 1, sent_text(segment)));
+    }
+    marked
+}
+
+// Corrected text by marker number. If the model repeats a marker, the
+// first occurrence wins.
+pub fn parse_segments(raw: &str) -> BTreeMap<usize, String> {
+    let raw = strip_code_fence(raw);
+    // (marker start, text start, number)
+    let mut markers: Vec<(usize, usize, usize)> = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = raw[pos..].find("[[") {
+        let start = pos + found;
+        let digits = &raw[start + 2..];
+        let len = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+        pos = start + 2;
+        if len > 0 && digits[len..].starts_with("]]") {
+            if let Ok(number) = digits[..len].parse() {
+                markers.push((start, start + 2 + len + 2, number));
+                pos = start + 2 + len + 2;
+            }
+        }
+    }
+
+    let mut segments = BTreeMap::new();
+    for (i, &(_, text_start, number)) in markers.iter().enumerate() {
+        let text_end = markers.get(i + 1).map(|m| m.0).unwrap_or(raw.len());
+        segments
+            .entry(number)
+            .or_insert_with(|| raw[text_start..text_end].to_string());
+    }
+    segments
+}
+
+fn leading_whitespace(text: &str) -> &str {
+    &text[..text.len() - text.trim_start().len()]
+}
+
+fn trailing_whitespace(text: &str) -> &str {
+    &text[text.trim_end().len()..]
+}
+
+// Put corrected text back into the original DOM. Segments the model left
+// out or emptied keep their original text, and each keeps its surrounding
+// whitespace so words do not run into neighbouring tags.
+pub fn apply_segments(html: &str, corrections: &BTreeMap<usize, String>) -> String {
+    let mut document = Html::parse_fragment(html);
+    let segments = segments_of(&document);
+
+    for (i, segment) in segments.iter().enumerate() {
+        let corrected = match corrections.get(&(i + 1)) {
+            Some(corrected) => corrected.trim(),
+            None => continue,
+        };
+        if corrected.is_empty() || corrected == sent_text(segment).trim() {
+            continue;
+        }
+
+        let text = format!(
+            "{}{}{}",
+            leading_whitespace(&segment.text),
+            corrected,
+            trailing_whitespace(&segment.text)
+        );
+        if let Some(mut node) = document.tree.get_mut(segment.id) {
+            *node.value() = Node::Text(Text { text: text.into() });
+        }
+    }
+
+    document.root_element().inner_html()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = "<p>Teh <strong>quick</strong> fox <em>jumpd</em>.</p>\
+        <ol><li>One &amp; too</li><li>Three</li></ol><pre>let x  = 1;</pre>";
+
+    #[test]
+    fn test_marks_text_runs_per_block() {
+        assert_eq!(
+            mark_segments(HTML),
+            "[[1]]Teh [[2]]quick[[3]] fox [[4]]jumpd[[5]].\n[[6]]One & too\n[[7]]Three"
+        );
+    }
+
+    #[test]
+    fn test_corrections_keep_markup() {
+        let raw = "```\n[[1]]The [[2]]quick[[3]] fox [[4]]jumped[[5]].\n[[6]]One & two\n\
+            [[7]]Three\n[[6]]echoed\n```";
+        let corrections = parse_segments(raw);
+        assert_eq!(corrections.get(&6).map(String::as_str), Some("One & two\n"));
+
+        assert_eq!(
+            apply_segments(HTML, &corrections),
+            "<p>The <strong>quick</strong> fox <em>jumped</em>.</p>\
+             <ol><li>One &amp; two</li><li>Three</li></ol><pre>let x  = 1;</pre>"
+        );
+    }
+
+    #[test]
+    fn test_missing_and_empty_segments_are_kept() {
+        let mut corrections = BTreeMap::new();
+        corrections.insert(1, "   ".to_string());
+        corrections.insert(4, "jumped".to_string());
+        corrections.insert(99, "stray".to_string());
+
+        let html = apply_segments(HTML, &corrections);
+        assert!(html.starts_with("<p>Teh <strong>quick</strong> fox <em>jumped</em>.</p>"));
+    }
+}
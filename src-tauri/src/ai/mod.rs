@@ -1,4 +1,19 @@
+pub mod actions;
+pub mod chat;
 pub mod common;
+pub mod credentials;
+pub mod edits;
+pub mod error;
+pub mod jobs;
+pub mod markup;
+pub mod personas;
+pub mod presets;
+pub mod preview;
+pub mod project_review;
+pub mod prompts;
 pub mod proofread;
 pub mod review;
+pub mod sanitize;
+pub mod settings;
+pub mod stream;
 pub mod suggestions;
@@ -0,0 +1,339 @@
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::ai::common::chunker::merge_reports;
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{
+    complete, create_client, prepare_chunked_prompts, prepare_prompt, run_prompts,
+    strip_code_fence, PromptResults,
+};
+use crate::ai::error::AiError;
+use crate::ai::jobs::spawn_job;
+use crate::ai::presets::{Preset, PresetStore};
+use crate::ai::prompts::{render_template, PromptContext};
+use crate::ai::settings::{load_ai_settings, AiCommand, AiSettings, ModelParams};
+
+const SUMMARY_ROLE: &str = "You compare feedback from several beta readers on the same text. \
+Respond with JSON only, in the form {\"agreements\": [\"...\"], \"disagreements\": [\"...\"]}. \
+An agreement is a point raised by more than one reader; name the readers. A disagreement is a \
+point where readers' opinions differ; say who thinks what. Use an empty list if there are none.";
+
+// A beta reader with its own prompt. Reviews run with the review command's
+// model settings, plus `params`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    // A prompt template; see `render_template` for the variables
+    pub prompt: String,
+    #[serde(default)]
+    pub params: ModelParams,
+    // Built-in personas cannot be edited or deleted
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PersonaFeedback {
+    pub persona_id: String,
+    pub name: String,
+    pub feedback: Option<String>,
+    // One reader failing does not fail the panel
+    pub error: Option<AiError>,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelSummary {
+    pub agreements: Vec<String>,
+    pub disagreements: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PanelResult {
+    pub feedback: Vec<PersonaFeedback>,
+    // None if fewer than two readers answered or the summary failed
+    pub summary: Option<PanelSummary>,
+    // Why the summary failed; the feedback is still returned
+    pub summary_error: Option<AiError>,
+    pub cached: bool,
+}
+
+pub fn builtin_personas() -> Vec<Persona> {
+    [
+        (
+            "genre_fan",
+            "Genre fan",
+            "You are a devoted reader of this book's genre who has read its classics and its \
+             latest hits. Give your honest reaction to the text below as a fan: what delivers on \
+             the genre's promises, what feels derivative, and whether you would keep reading.",
+        ),
+        (
+            "line_editor",
+            "Line editor",
+            "You are an experienced line editor. Comment on the prose of the text below: rhythm, \
+             word choice, clarity, repetition and dialogue. Point to specific sentences rather \
+             than rewriting the text.",
+        ),
+        (
+            "sensitivity_reader",
+            "Sensitivity reader",
+            "You are a sensitivity reader. Note any portrayals, language or assumptions in the \
+             text below that readers may find inaccurate, stereotyped or hurtful, explain why, \
+             and suggest directions for revision. Say so plainly if you find nothing of concern.",
+        ),
+        (
+            "first_time_reader",
+            "First-time reader",
+            "You are reading the text below with no knowledge of the story so far. Describe \
+             where you were confused, curious, bored or hooked, and what you expect to happen \
+             next.",
+        ),
+    ]
+    .into_iter()
+    .map(|(id, name, prompt)| Persona {
+        id: id.to_string(),
+        name: name.to_string(),
+        prompt: prompt.to_string(),
+        params: ModelParams::default(),
+        builtin: true,
+    })
+    .collect()
+}
+
+impl Preset for Persona {
+    const NOUN: &'static str = "persona";
+    const FILE: &'static str = "ai_personas.json";
+
+    fn builtins() -> Vec<Self> {
+        builtin_personas()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn set_builtin(&mut self, builtin: bool) {
+        self.builtin = builtin;
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() || self.prompt.trim().is_empty() {
+            return Err("A persona needs a name and a prompt.".to_string());
+        }
+        Ok(())
+    }
+}
+
+// The summary request's input: each reader's feedback under their name
+fn summary_input(feedback: &[PersonaFeedback]) -> String {
+    feedback
+        .iter()
+        .filter_map(|f| {
+            f.feedback
+                .as_ref()
+                .map(|text| format!("## {}\n\n{}", f.name, text.trim()))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn parse_summary(raw: &str) -> Result<PanelSummary, String> {
+    serde_json::from_str(strip_code_fence(raw))
+        .map_err(|e| format!("Panel summary was not valid JSON: {}", e))
+}
+
+#[tauri::command]
+pub fn list_personas(app: AppHandle) -> Result<Vec<Persona>, String> {
+    PresetStore::<Persona>::open(&app)?.list()
+}
+
+#[tauri::command]
+pub fn save_persona(app: AppHandle, persona: Persona) -> Result<Persona, String> {
+    PresetStore::open(&app)?.upsert(persona)
+}
+
+#[tauri::command]
+pub fn delete_persona(app: AppHandle, id: String) -> Result<(), String> {
+    PresetStore::<Persona>::open(&app)?.delete(&id)
+}
+
+async fn persona_review(
+    settings: &AiSettings,
+    scope: &UsageScope,
+    persona: &Persona,
+    content: &str,
+    context: &PromptContext,
+) -> Result<PromptResults, AiError> {
+    let system_role_content = render_template(&persona.prompt, context);
+    let mut prompts = prepare_chunked_prompts(
+        settings,
+        AiCommand::Review,
+        scope,
+        &system_role_content,
+        content,
+    )?;
+    for prompt in &mut prompts {
+        prompt.override_params(&persona.params);
+    }
+    run_prompts(&prompts, settings.chunking.max_concurrency).await
+}
+
+async fn run_panel(
+    app: AppHandle,
+    persona_ids: Option<Vec<String>>,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<PanelResult, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "run_persona_panel", context.project_name.clone())?;
+
+    let personas: Vec<Persona> = match &persona_ids {
+        Some(ids) => {
+            let all = list_personas(app.clone())?;
+            ids.iter()
+                .map(|id| {
+                    all.iter()
+                        .find(|p| p.id == *id)
+                        .cloned()
+                        .ok_or_else(|| format!("Persona {} not found", id))
+                })
+                .collect::<Result<_, _>>()?
+        }
+        None => builtin_personas(),
+    };
+    if personas.is_empty() {
+        return Err("Choose at least one persona.".to_string().into());
+    }
+
+    // Every reader at once
+    let results = join_all(
+        personas
+            .iter()
+            .map(|persona| persona_review(&settings, &scope, persona, &content, &context)),
+    )
+    .await;
+
+    let mut feedback = Vec::new();
+    let mut first_error = None;
+    for (persona, result) in personas.iter().zip(results) {
+        let (text, error, cached) = match result {
+            Ok(results) => (Some(merge_reports(&results.parts)), None, results.cached),
+            Err(e) => {
+                first_error.get_or_insert_with(|| e.clone());
+                (None, Some(e), false)
+            }
+        };
+        feedback.push(PersonaFeedback {
+            persona_id: persona.id.clone(),
+            name: persona.name.clone(),
+            feedback: text,
+            error,
+            cached,
+        });
+    }
+
+    let answered = feedback.iter().filter(|f| f.feedback.is_some()).count();
+    if answered == 0 {
+        if let Some(error) = first_error {
+            return Err(error);
+        }
+    }
+
+    let mut cached = feedback.iter().all(|f| f.cached);
+    let mut summary = None;
+    let mut summary_error = None;
+    if answered >= 2 {
+        match summarize(&settings, &scope, &feedback).await {
+            Ok((parsed, summary_cached)) => {
+                cached &= summary_cached;
+                summary = Some(parsed);
+            }
+            Err(e) => summary_error = Some(e),
+        }
+    }
+
+    Ok(PanelResult {
+        feedback,
+        summary,
+        summary_error,
+        cached,
+    })
+}
+
+// Compare the readers' feedback; also returns whether it was cached
+async fn summarize(
+    settings: &AiSettings,
+    scope: &UsageScope,
+    feedback: &[PersonaFeedback],
+) -> Result<(PanelSummary, bool), AiError> {
+    let prompt = prepare_prompt(
+        settings,
+        AiCommand::Review,
+        scope,
+        SUMMARY_ROLE,
+        summary_input(feedback),
+    )?;
+    let completion = complete(&create_client(), &prompt).await?;
+    let summary = parse_summary(&completion.text).map_err(AiError::malformed)?;
+    Ok((summary, completion.cached))
+}
+
+// Review content with several personas at once. Runs every built-in
+// persona unless `persona_ids` picks some.
+#[tauri::command]
+pub fn run_persona_panel(
+    app: AppHandle,
+    persona_ids: Option<Vec<String>>,
+    content: String,
+    context: Option<PromptContext>,
+) -> String {
+    spawn_job(
+        &app,
+        "run_persona_panel",
+        run_panel(app.clone(), persona_ids, content, context),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feedback(name: &str, text: Option<&str>) -> PersonaFeedback {
+        PersonaFeedback {
+            persona_id: name.to_lowercase(),
+            name: name.to_string(),
+            feedback: text.map(|t| t.to_string()),
+            error: None,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_summary_input_skips_failed_readers() {
+        let input = summary_input(&[
+            feedback("Line editor", Some("Too many adverbs.\n")),
+            feedback("Genre fan", None),
+            feedback("First-time reader", Some("Lost in chapter two.")),
+        ]);
+        assert_eq!(
+            input,
+            "## Line editor\n\nToo many adverbs.\n\n## First-time reader\n\nLost in chapter two."
+        );
+    }
+
+    #[test]
+    fn test_parse_summary() {
+        let summary = parse_summary("```json\n{\"agreements\": [\"Pacing drags\"]}\n```").unwrap();
+        assert_eq!(summary.agreements, vec!["Pacing drags"]);
+        assert!(summary.disagreements.is_empty());
+        assert!(parse_summary("They mostly agree.").is_err());
+    }
+}
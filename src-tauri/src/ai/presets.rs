@@ -0,0 +1,209 @@
+use std::fs;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::ai::settings::user_data_path;
+
+// A user-editable entry that ships with built-in defaults, such as an AI
+// action or a persona. Built-ins are never stored and cannot be edited or
+// deleted.
+pub trait Preset: Clone + Serialize + DeserializeOwned {
+    // Singular name for messages, e.g. "persona"
+    const NOUN: &'static str;
+    // File in the user data directory holding the custom presets
+    const FILE: &'static str;
+
+    fn builtins() -> Vec<Self>;
+    fn id(&self) -> &str;
+    fn set_id(&mut self, id: String);
+    fn set_builtin(&mut self, builtin: bool);
+    // Why the preset cannot be saved, if anything
+    fn validate(&self) -> Result<(), String>;
+}
+
+pub fn is_builtin<T: Preset>(id: &str) -> bool {
+    T::builtins().iter().any(|p| p.id() == id)
+}
+
+fn builtin_error<T: Preset>(id: &str) -> String {
+    format!("\"{}\" is a built-in {}", id, T::NOUN)
+}
+
+// Insert or replace a preset; an empty id creates a new one
+fn upsert<T: Preset>(presets: &mut Vec<T>, mut preset: T) -> Result<T, String> {
+    preset.validate()?;
+    if is_builtin::<T>(preset.id()) {
+        return Err(builtin_error::<T>(preset.id()));
+    }
+    preset.set_builtin(false);
+
+    if preset.id().is_empty() {
+        preset.set_id(uuid::Uuid::new_v4().to_string());
+        presets.push(preset.clone());
+        return Ok(preset);
+    }
+
+    match presets.iter_mut().find(|p| p.id() == preset.id()) {
+        Some(existing) => *existing = preset.clone(),
+        None => presets.push(preset.clone()),
+    }
+    Ok(preset)
+}
+
+// The custom presets of one kind, saved as a JSON list
+pub struct PresetStore<T: Preset> {
+    path: PathBuf,
+    kind: PhantomData<T>,
+}
+
+impl<T: Preset> PresetStore<T> {
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        Ok(Self::at(user_data_path(app, T::FILE)?))
+    }
+
+    fn at(path: PathBuf) -> Self {
+        PresetStore {
+            path,
+            kind: PhantomData,
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<T>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let raw = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}s: {}", T::NOUN, e))?;
+        serde_json::from_str(&raw).map_err(|e| format!("Failed to parse {}s: {}", T::NOUN, e))
+    }
+
+    pub fn save(&self, presets: &[T]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+
+        let raw = serde_json::to_string_pretty(presets)
+            .map_err(|e| format!("Failed to serialize {}s: {}", T::NOUN, e))?;
+        fs::write(&self.path, raw).map_err(|e| format!("Failed to write {}s: {}", T::NOUN, e))
+    }
+
+    // Built-in presets first, then custom ones
+    pub fn list(&self) -> Result<Vec<T>, String> {
+        let mut presets = T::builtins();
+        presets.extend(self.load()?);
+        Ok(presets)
+    }
+
+    pub fn upsert(&self, preset: T) -> Result<T, String> {
+        let mut presets = self.load()?;
+        let saved = upsert(&mut presets, preset)?;
+        self.save(&presets)?;
+        Ok(saved)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        if is_builtin::<T>(id) {
+            return Err(builtin_error::<T>(id));
+        }
+        let mut presets = self.load()?;
+        presets.retain(|p| p.id() != id);
+        self.save(&presets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Snippet {
+        id: String,
+        name: String,
+        #[serde(default, skip_deserializing)]
+        builtin: bool,
+    }
+
+    impl Preset for Snippet {
+        const NOUN: &'static str = "snippet";
+        const FILE: &'static str = "snippets.json";
+
+        fn builtins() -> Vec<Self> {
+            vec![snippet("greeting", "Greeting")]
+        }
+
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn set_id(&mut self, id: String) {
+            self.id = id;
+        }
+
+        fn set_builtin(&mut self, builtin: bool) {
+            self.builtin = builtin;
+        }
+
+        fn validate(&self) -> Result<(), String> {
+            if self.name.trim().is_empty() {
+                return Err("A snippet needs a name.".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    fn snippet(id: &str, name: &str) -> Snippet {
+        Snippet {
+            id: id.to_string(),
+            name: name.to_string(),
+            builtin: true,
+        }
+    }
+
+    #[test]
+    fn test_upsert_creates_and_replaces() {
+        let mut snippets = Vec::new();
+        let created = upsert(&mut snippets, snippet("", "Sign-off")).unwrap();
+        assert!(!created.id.is_empty());
+        assert!(!created.builtin);
+
+        let mut renamed = created.clone();
+        renamed.name = "Farewell".to_string();
+        upsert(&mut snippets, renamed).unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].name, "Farewell");
+    }
+
+    #[test]
+    fn test_builtins_are_protected() {
+        let mut snippets = Vec::new();
+        assert!(upsert(&mut snippets, snippet("greeting", "Mine")).is_err());
+        assert!(upsert(&mut snippets, snippet("", " ")).is_err());
+        assert!(snippets.is_empty());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("wm9000-presets-{}", uuid::Uuid::new_v4()));
+        let store = PresetStore::<Snippet>::at(dir.join(Snippet::FILE));
+        assert!(store.load().unwrap().is_empty());
+
+        let saved = store.upsert(snippet("", "Sign-off")).unwrap();
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed[0].builtin);
+        assert!(!listed[1].builtin);
+
+        assert!(store.delete("greeting").is_err());
+        store.delete(&saved.id).unwrap();
+        assert!(store.load().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::ai::common::cache::cached_response;
+use crate::ai::common::chunker::estimate_tokens;
+use crate::ai::common::usage::{cost_of, price_for, UsageScope};
+use crate::ai::common::{
+    add_chunk_context, chunk_content, create_client, create_provider, create_provider_with_key,
+    prompt_with_provider, AiProvider,
+};
+use crate::ai::error::AiError;
+use crate::ai::prompts::{resolve_system_prompt, PromptContext};
+use crate::ai::settings::{load_ai_settings, AiCommand, AiSettings, ModelParams};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewRequest {
+    pub url: String,
+    // Exactly the JSON that would be sent; credentials go in headers and
+    // are not shown
+    pub body: serde_json::Value,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    // Would be answered from the response cache without a request
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestPreview {
+    pub command: AiCommand,
+    pub provider: String,
+    pub model: String,
+    pub params: ModelParams,
+    pub requests: Vec<PreviewRequest>,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    // Excludes cached requests. None if the model has no known price.
+    pub estimated_cost: Option<f64>,
+}
+
+// Assume a response about as long as its content, capped by max_tokens
+fn estimate_completion_tokens(content_tokens: usize, max_tokens: Option<u32>) -> usize {
+    match max_tokens {
+        Some(max) => content_tokens.min(max as usize),
+        None => content_tokens,
+    }
+}
+
+// A preview needs no key, so stand one in if none is configured yet
+fn preview_provider(
+    app: &AppHandle,
+    settings: &AiSettings,
+) -> Result<Box<dyn AiProvider>, AiError> {
+    match create_provider(app, settings) {
+        Err(AiError::MissingKey { .. }) | Err(AiError::CredentialsLocked) => {
+            create_provider_with_key(app, settings, Some("<api key>".to_string()))
+        }
+        other => other,
+    }
+}
+
+// Build the requests `proofread_content`, `ai_suggestions` or `ai_review`
+// would send for this input, without sending anything
+#[tauri::command]
+pub fn preview_ai_request(
+    app: AppHandle,
+    command: AiCommand,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<RequestPreview, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "preview", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, command, &context)?;
+
+    let client = create_client();
+    let mut requests = Vec::new();
+    let mut model = String::new();
+    let mut provider_id = String::new();
+    let mut params = settings.params_for(command);
+
+    for chunk in chunk_content(&settings, &content) {
+        let mut prompt = prompt_with_provider(
+            preview_provider(&app, &settings)?,
+            &settings,
+            command,
+            &scope,
+            &system_role_content,
+            chunk.text.clone(),
+            false,
+        )?;
+        add_chunk_context(&mut prompt, &chunk);
+
+        let request = prompt
+            .build_request(&client, false)
+            .build()
+            .map_err(|e| format!("Failed to build request: {}", e))?;
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice(bytes).ok())
+            .unwrap_or(serde_json::Value::Null);
+
+        requests.push(PreviewRequest {
+            url: request.url().to_string(),
+            body,
+            prompt_tokens: prompt
+                .messages
+                .iter()
+                .map(|m| estimate_tokens(&m.content))
+                .sum(),
+            completion_tokens: estimate_completion_tokens(
+                estimate_tokens(&chunk.text),
+                prompt.params.max_tokens,
+            ),
+            cached: cached_response(&prompt).is_some(),
+        });
+
+        model = prompt.model.clone();
+        provider_id = prompt.provider.id().to_string();
+        params = prompt.params.clone();
+    }
+
+    let prompt_tokens = requests.iter().map(|r| r.prompt_tokens).sum();
+    let completion_tokens = requests.iter().map(|r| r.completion_tokens).sum();
+    let estimated_cost = price_for(&settings.prices, &model).map(|price| {
+        requests
+            .iter()
+            .filter(|r| !r.cached)
+            .map(|r| cost_of(price, r.prompt_tokens as u64, r.completion_tokens as u64))
+            .sum()
+    });
+
+    Ok(RequestPreview {
+        command,
+        provider: provider_id,
+        model,
+        params,
+        requests,
+        prompt_tokens,
+        completion_tokens,
+        estimated_cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_estimate_is_capped() {
+        assert_eq!(estimate_completion_tokens(800, None), 800);
+        assert_eq!(estimate_completion_tokens(800, Some(500)), 500);
+        assert_eq!(estimate_completion_tokens(200, Some(500)), 200);
+    }
+}
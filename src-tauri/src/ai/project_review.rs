@@ -0,0 +1,458 @@
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::ai::common::chunker::{estimate_tokens, merge_reports};
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{complete, create_client, prepare_prompt, run_chunked, strip_code_fence};
+use crate::ai::error::AiError;
+use crate::ai::jobs::spawn_job;
+use crate::ai::settings::{load_ai_settings, AiCommand, AiSettings};
+use crate::export::compiler::{compile_chapters, BlockType, Chapter};
+use crate::export::types::ExportFileNode;
+
+const CHAPTER_REVIEW_ROLE: &str = "You are an expert developmental editor reviewing one chapter \
+of a longer manuscript. Summarize what happens, then give concise feedback on pacing, \
+character development, continuity and prose.";
+
+const OVERALL_REVIEW_ROLE: &str = "You are an expert developmental editor. Below are reviews of \
+every chapter of a manuscript, in order. Assess the manuscript as a whole. Respond with JSON \
+only, in the form {\"overview\": \"...\", \"structure\": \"...\", \"pacing\": \"...\", \
+\"character_arcs\": \"...\", \"consistency\": \"...\"}.";
+
+const CONDENSE_REVIEWS_ROLE: &str = "You are an expert developmental editor. Below are reviews \
+of consecutive chapters of a manuscript. Condense each review, keeping its chapter heading and \
+the points that matter for the manuscript as a whole.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterReview {
+    pub title: String,
+    pub feedback: String,
+    // The chapter was unchanged since an earlier review
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverallReview {
+    pub overview: String,
+    pub structure: String,
+    pub pacing: String,
+    pub character_arcs: String,
+    pub consistency: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectReview {
+    pub project_name: String,
+    pub created: String,
+    pub chapters: Vec<ChapterReview>,
+    pub overall: OverallReview,
+    // Why the review stopped early. Chapters reviewed before then are kept,
+    // and the overall assessment is left empty.
+    #[serde(default)]
+    pub error: Option<String>,
+    // Where the review was saved in the project, as JSON and Markdown
+    pub json_path: Option<String>,
+    pub markdown_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewProgress {
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+}
+
+fn emit_progress(app: &AppHandle, stage: &str, current: usize, total: usize) {
+    let _ = app.emit(
+        "ai-review-progress",
+        ReviewProgress {
+            stage: stage.to_string(),
+            current,
+            total,
+        },
+    );
+}
+
+// Plain text of a compiled chapter, which costs far fewer tokens than HTML
+pub fn chapter_text(chapter: &Chapter) -> String {
+    let mut text = String::new();
+    for section in &chapter.sections {
+        text.push_str(&format!("## {}\n\n", section.title));
+        let mut in_item = false;
+        for element in &section.elements {
+            match element.block_type {
+                BlockType::Paragraph => text.push_str(&element.text),
+                BlockType::ParagraphBreak => text.push_str("\n\n"),
+                BlockType::ListItem => {
+                    if !in_item {
+                        text.push_str("- ");
+                        in_item = true;
+                    }
+                    text.push_str(&element.text);
+                }
+                BlockType::ListItemBreak => {
+                    text.push('\n');
+                    in_item = false;
+                }
+                BlockType::Heading => text.push_str(&format!("### {}\n\n", element.text)),
+            }
+        }
+        text.push_str("\n\n");
+    }
+    text
+}
+
+// Group chapter reviews, in order, into batches that fit the chunk budget.
+// A review is never split, so one longer than the budget is a batch alone.
+fn digest_batches(reviews: &[String], max_tokens: usize) -> Vec<String> {
+    let mut batches: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for review in reviews {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(review) > max_tokens {
+            batches.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(review);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+fn parse_overall(raw: &str) -> OverallReview {
+    serde_json::from_str(strip_code_fence(raw)).unwrap_or_else(|_| OverallReview {
+        // Keep the model's answer even if it ignored the JSON format
+        overview: raw.trim().to_string(),
+        ..Default::default()
+    })
+}
+
+impl ProjectReview {
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!(
+            "# Manuscript review: {}\n\n_{}_\n\n",
+            self.project_name, self.created
+        );
+        if let Some(error) = &self.error {
+            md.push_str(&format!("_This review is incomplete: {}_\n\n", error));
+        }
+
+        let sections = [
+            ("Overview", &self.overall.overview),
+            ("Structure", &self.overall.structure),
+            ("Pacing", &self.overall.pacing),
+            ("Character arcs", &self.overall.character_arcs),
+            ("Consistency", &self.overall.consistency),
+        ];
+        for (heading, body) in sections {
+            if !body.trim().is_empty() {
+                md.push_str(&format!("## {}\n\n{}\n\n", heading, body.trim()));
+            }
+        }
+
+        md.push_str("## Chapters\n\n");
+        for chapter in &self.chapters {
+            md.push_str(&format!(
+                "### {}\n\n{}\n\n",
+                chapter.title,
+                chapter.feedback.trim()
+            ));
+        }
+        md
+    }
+
+    fn save(&mut self, reviews_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(reviews_dir)
+            .map_err(|e| format!("Failed to create reviews directory: {}", e))?;
+
+        let stem = format!("review_{}", Local::now().format("%Y%m%d_%H%M%S"));
+        let json_path = reviews_dir.join(format!("{}.json", stem));
+        let markdown_path = reviews_dir.join(format!("{}.md", stem));
+        self.json_path = Some(json_path.to_string_lossy().to_string());
+        self.markdown_path = Some(markdown_path.to_string_lossy().to_string());
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize review: {}", e))?;
+        fs::write(&json_path, json).map_err(|e| format!("Failed to write review: {}", e))?;
+        fs::write(&markdown_path, self.to_markdown())
+            .map_err(|e| format!("Failed to write review: {}", e))
+    }
+}
+
+// Review chapters in order with `review`, stopping at the first failure.
+// Returns the reviews finished before it along with the error.
+async fn review_chapters<F, Fut>(
+    chapters: &[Chapter],
+    mut review: F,
+) -> (Vec<ChapterReview>, Option<AiError>)
+where
+    F: FnMut(usize, &Chapter) -> Fut,
+    Fut: Future<Output = Result<ChapterReview, AiError>>,
+{
+    let mut reviews = Vec::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        match review(i, chapter).await {
+            Ok(chapter_review) => reviews.push(chapter_review),
+            Err(e) => return (reviews, Some(e)),
+        }
+    }
+    (reviews, None)
+}
+
+// Assess the manuscript from its chapter reviews. The answer must come back
+// as one JSON object, so the input is condensed until it fits a single
+// prompt rather than chunked.
+async fn review_overall(
+    settings: &AiSettings,
+    scope: &UsageScope,
+    chapter_reviews: &[ChapterReview],
+) -> Result<OverallReview, AiError> {
+    let client = create_client();
+    let max_tokens = settings.chunking.max_chunk_tokens;
+    let digest: Vec<String> = chapter_reviews
+        .iter()
+        .map(|c| format!("# {}\n\n{}", c.title, c.feedback))
+        .collect();
+    let mut batches = digest_batches(&digest, max_tokens);
+    while batches.len() > 1 {
+        let mut condensed = Vec::new();
+        for batch in &batches {
+            let prompt = prepare_prompt(
+                settings,
+                AiCommand::Review,
+                scope,
+                CONDENSE_REVIEWS_ROLE,
+                batch.clone(),
+            )?;
+            condensed.push(complete(&client, &prompt).await?.text);
+        }
+        let next = digest_batches(&condensed, max_tokens);
+        // Send what is left as is if condensing stops helping
+        let shrank = next.len() < batches.len();
+        batches = next;
+        if !shrank {
+            break;
+        }
+    }
+
+    let prompt = prepare_prompt(
+        settings,
+        AiCommand::Review,
+        scope,
+        OVERALL_REVIEW_ROLE,
+        batches.join("\n\n"),
+    )?;
+    Ok(parse_overall(&complete(&client, &prompt).await?.text))
+}
+
+// Review every chapter of the project, then the manuscript as a whole.
+// Takes the same nodes as `ExportPayload.nodes`.
+async fn review_project(
+    app: AppHandle,
+    project_name: String,
+    nodes: Vec<ExportFileNode>,
+) -> Result<ProjectReview, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let scope = UsageScope::new(&app, "ai_project_review", Some(project_name.clone()))?;
+    let chapters = compile_chapters(&nodes);
+    if chapters.is_empty() {
+        return Err("The project has no chapters to review.".to_string().into());
+    }
+
+    // One step per chapter, plus the overall pass
+    let total_steps = chapters.len() + 1;
+    let (chapter_reviews, mut error) = review_chapters(&chapters, |i, chapter| {
+        emit_progress(
+            &app,
+            &format!("Reviewing chapter {} of {}...", i + 1, chapters.len()),
+            i,
+            total_steps,
+        );
+        let (settings, scope) = (&settings, &scope);
+        let title = chapter.title.clone();
+        let text = chapter_text(chapter);
+        async move {
+            let results = run_chunked(
+                settings,
+                AiCommand::Review,
+                scope,
+                CHAPTER_REVIEW_ROLE,
+                &text,
+            )
+            .await?;
+            Ok(ChapterReview {
+                title,
+                feedback: merge_reports(&results.parts),
+                cached: results.cached,
+            })
+        }
+    })
+    .await;
+
+    let mut overall = OverallReview::default();
+    if error.is_none() {
+        emit_progress(
+            &app,
+            "Reviewing the manuscript as a whole...",
+            chapters.len(),
+            total_steps,
+        );
+        match review_overall(&settings, &scope, &chapter_reviews).await {
+            Ok(review) => overall = review,
+            Err(e) => error = Some(e),
+        }
+    }
+    // Nothing was reviewed, so there is nothing worth keeping
+    if chapter_reviews.is_empty() {
+        if let Some(error) = error {
+            return Err(error);
+        }
+    }
+
+    let mut review = ProjectReview {
+        project_name: project_name.clone(),
+        created: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        chapters: chapter_reviews,
+        overall,
+        error: error.map(|e| e.to_string()),
+        json_path: None,
+        markdown_path: None,
+    };
+
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    let reviews_dir = app_data_dir
+        .join("Projects")
+        .join(&project_name)
+        .join("reviews");
+    review.save(&reviews_dir)?;
+
+    let stage = match review.error {
+        Some(_) => "Review stopped early",
+        None => "Review complete",
+    };
+    emit_progress(&app, stage, total_steps, total_steps);
+
+    Ok(review)
+}
+
+#[tauri::command]
+pub fn ai_project_review(
+    app: AppHandle,
+    project_name: String,
+    nodes: Vec<ExportFileNode>,
+) -> String {
+    spawn_job(
+        &app,
+        "ai_project_review",
+        review_project(app.clone(), project_name, nodes),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::{Section, TextElement};
+    use futures_util::FutureExt;
+
+    #[test]
+    fn test_chapter_text_flattens_elements() {
+        let element = |text: &str, block_type: BlockType| TextElement {
+            text: text.to_string(),
+            bold: false,
+            italic: false,
+            block_type,
+        };
+        let chapter = Chapter {
+            title: "Chapter 1".to_string(),
+            sections: vec![Section {
+                title: "Opening".to_string(),
+                elements: vec![
+                    element("It was ", BlockType::Paragraph),
+                    element("dark.", BlockType::Paragraph),
+                    element("", BlockType::ParagraphBreak),
+                    element("Rain ", BlockType::ListItem),
+                    element("again", BlockType::ListItem),
+                    element("", BlockType::ListItemBreak),
+                    element("Wind", BlockType::ListItem),
+                    element("", BlockType::ListItemBreak),
+                ],
+            }],
+        };
+        assert_eq!(
+            chapter_text(&chapter),
+            "## Opening\n\nIt was dark.\n\n- Rain again\n- Wind\n\n\n"
+        );
+    }
+
+    #[test]
+    fn test_overall_falls_back_to_raw_text() {
+        let parsed = parse_overall("```json\n{\"pacing\": \"Slow middle\"}\n```");
+        assert_eq!(parsed.pacing, "Slow middle");
+        assert!(parsed.overview.is_empty());
+
+        let fallback = parse_overall("A strong debut.");
+        assert_eq!(fallback.overview, "A strong debut.");
+    }
+
+    #[test]
+    fn test_digest_over_chunk_limit_batches_whole_reviews() {
+        let reviews: Vec<String> = (1..=6)
+            .map(|i| format!("# Chapter {}\n\n{}", i, "Strong pacing. ".repeat(40)))
+            .collect();
+        let max_tokens = estimate_tokens(&reviews[0]) * 2 + 10;
+        assert!(estimate_tokens(&reviews.join("\n\n")) > max_tokens);
+
+        let batches = digest_batches(&reviews, max_tokens);
+        assert_eq!(batches.len(), 3);
+        assert!(batches.iter().all(|b| estimate_tokens(b) <= max_tokens));
+        assert_eq!(batches.join("\n\n"), reviews.join("\n\n"));
+
+        // A review over the limit on its own is kept whole
+        let batches = digest_batches(&reviews[..2], 10);
+        assert_eq!(batches, reviews[..2].to_vec());
+    }
+
+    #[test]
+    fn test_later_chapter_failure_keeps_earlier_reviews() {
+        let chapter = |title: &str| Chapter {
+            title: title.to_string(),
+            sections: Vec::new(),
+        };
+        let chapters = vec![chapter("One"), chapter("Two"), chapter("Three")];
+        let mut attempted = Vec::new();
+
+        let (reviews, error) = review_chapters(&chapters, |i, chapter| {
+            attempted.push(chapter.title.clone());
+            std::future::ready(if i == 1 {
+                Err(AiError::Timeout)
+            } else {
+                Ok(ChapterReview {
+                    title: chapter.title.clone(),
+                    feedback: "Fine.".to_string(),
+                    cached: false,
+                })
+            })
+        })
+        .now_or_never()
+        .unwrap();
+
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].title, "One");
+        assert_eq!(error, Some(AiError::Timeout));
+        // Nothing more is paid for once a chapter fails
+        assert_eq!(attempted, vec!["One", "Two"]);
+    }
+}
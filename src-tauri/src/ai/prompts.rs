@@ -0,0 +1,414 @@
+use std::fs;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::ai::settings::{user_data_path, AiCommand};
+
+const PROMPTS_FILE: &str = "prompts.json";
+
+// Built-in prompts, used when no template is active for a command
+pub fn default_prompt(command: AiCommand) -> &'static str {
+    match command {
+        AiCommand::Proofread => {
+            "You are a professional editor. Proofread the content below and return it as clean HTML:"
+        }
+        AiCommand::Suggestions => {
+            "You are an expert writing assistant. Provide suggestions for improvement to the following content:"
+        }
+        AiCommand::Review => {
+            "You are an expert content reviewer. Provide detailed feedback on the following content:"
+        }
+    }
+}
+
+// Prompts used to be customised through .env; keep honouring those
+fn legacy_env_prompt(command: AiCommand) -> Option<String> {
+    let var = match command {
+        AiCommand::Proofread => "PROOFREAD_SYSTEM_ROLE",
+        AiCommand::Suggestions => "SUGGESTIONS_SYSTEM_ROLE",
+        AiCommand::Review => "REVIEW_SYSTEM_ROLE",
+    };
+    std::env::var(var).ok()
+}
+
+// Values for the `{{variable}}` placeholders in a template
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptContext {
+    // Also used to attribute usage to the project
+    pub project_name: Option<String>,
+    pub project_type: Option<String>,
+    pub genre: Option<String>,
+    pub style_guide: Option<String>,
+    pub file_name: Option<String>,
+}
+
+impl PromptContext {
+    fn value(&self, name: &str) -> Option<&str> {
+        match name {
+            "project_name" => self.project_name.as_deref(),
+            "project_type" => self.project_type.as_deref(),
+            "genre" => self.genre.as_deref(),
+            "style_guide" => self.style_guide.as_deref(),
+            "file_name" => self.file_name.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+// Replace `{{name}}` placeholders; unknown or unset variables become empty
+pub fn render_template(body: &str, context: &PromptContext) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        match rest[start + 2..].find("}}") {
+            Some(len) => {
+                let name = rest[start + 2..start + 2 + len].trim();
+                out.push_str(context.value(name).unwrap_or(""));
+                rest = &rest[start + 2 + len + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptVersion {
+    pub version: u32,
+    pub body: String,
+    pub updated: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub command: AiCommand,
+    pub body: String,
+    pub version: u32,
+    pub updated: String,
+    // Earlier versions, oldest first
+    #[serde(default)]
+    pub history: Vec<PromptVersion>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActiveTemplates {
+    pub proofread: Option<String>,
+    pub suggestions: Option<String>,
+    pub review: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptLibrary {
+    pub templates: Vec<PromptTemplate>,
+    pub active: ActiveTemplates,
+}
+
+fn now() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+impl PromptLibrary {
+    fn active_id(&self, command: AiCommand) -> Option<&String> {
+        match command {
+            AiCommand::Proofread => self.active.proofread.as_ref(),
+            AiCommand::Suggestions => self.active.suggestions.as_ref(),
+            AiCommand::Review => self.active.review.as_ref(),
+        }
+    }
+
+    fn active_id_mut(&mut self, command: AiCommand) -> &mut Option<String> {
+        match command {
+            AiCommand::Proofread => &mut self.active.proofread,
+            AiCommand::Suggestions => &mut self.active.suggestions,
+            AiCommand::Review => &mut self.active.review,
+        }
+    }
+
+    pub fn find(&self, id: &str) -> Option<&PromptTemplate> {
+        self.templates.iter().find(|t| t.id == id)
+    }
+
+    pub fn active_template(&self, command: AiCommand) -> Option<&PromptTemplate> {
+        self.active_id(command).and_then(|id| self.find(id))
+    }
+
+    pub fn create(&mut self, name: String, command: AiCommand, body: String) -> PromptTemplate {
+        let template = PromptTemplate {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            command,
+            body,
+            version: 1,
+            updated: now(),
+            history: Vec::new(),
+        };
+        self.templates.push(template.clone());
+        template
+    }
+
+    // Saving a new body bumps the version and keeps the old one in history
+    pub fn update(
+        &mut self,
+        id: &str,
+        name: Option<String>,
+        body: Option<String>,
+    ) -> Result<PromptTemplate, String> {
+        let template = self
+            .templates
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Prompt template {} not found", id))?;
+
+        if let Some(name) = name {
+            template.name = name;
+        }
+        if let Some(body) = body {
+            if body != template.body {
+                template.history.push(PromptVersion {
+                    version: template.version,
+                    body: std::mem::replace(&mut template.body, body),
+                    updated: template.updated.clone(),
+                });
+                template.version += 1;
+            }
+        }
+        template.updated = now();
+        Ok(template.clone())
+    }
+
+    pub fn delete(&mut self, id: &str) {
+        self.templates.retain(|t| t.id != id);
+        for command in [
+            AiCommand::Proofread,
+            AiCommand::Suggestions,
+            AiCommand::Review,
+        ] {
+            let active = self.active_id_mut(command);
+            if active.as_deref() == Some(id) {
+                *active = None;
+            }
+        }
+    }
+
+    pub fn set_active(&mut self, command: AiCommand, id: Option<String>) -> Result<(), String> {
+        if let Some(ref id) = id {
+            let template = self
+                .find(id)
+                .ok_or_else(|| format!("Prompt template {} not found", id))?;
+            if template.command != command {
+                return Err(format!(
+                    "Prompt template \"{}\" is not a {:?} prompt",
+                    template.name, command
+                ));
+            }
+        }
+        *self.active_id_mut(command) = id;
+        Ok(())
+    }
+
+    // Imported templates always get fresh ids so they never overwrite ours
+    pub fn import(&mut self, templates: Vec<PromptTemplate>) -> usize {
+        let count = templates.len();
+        for mut template in templates {
+            template.id = uuid::Uuid::new_v4().to_string();
+            self.templates.push(template);
+        }
+        count
+    }
+}
+
+pub fn load_prompt_library(app: &AppHandle) -> Result<PromptLibrary, String> {
+    let path = user_data_path(app, PROMPTS_FILE)?;
+    if !path.exists() {
+        return Ok(PromptLibrary::default());
+    }
+
+    let raw =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read prompt library: {}", e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse prompt library: {}", e))
+}
+
+fn save_prompt_library(app: &AppHandle, library: &PromptLibrary) -> Result<(), String> {
+    let path = user_data_path(app, PROMPTS_FILE)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+
+    let raw = serde_json::to_string_pretty(library)
+        .map_err(|e| format!("Failed to serialize prompt library: {}", e))?;
+    fs::write(&path, raw).map_err(|e| format!("Failed to write prompt library: {}", e))
+}
+
+// The system prompt for a command: the active template, else a legacy
+// .env prompt, else the built-in default
+pub fn resolve_system_prompt(
+    app: &AppHandle,
+    command: AiCommand,
+    context: &PromptContext,
+) -> Result<String, String> {
+    let library = load_prompt_library(app)?;
+    let body = match library.active_template(command) {
+        Some(template) => template.body.clone(),
+        None => legacy_env_prompt(command).unwrap_or_else(|| default_prompt(command).to_string()),
+    };
+    Ok(render_template(&body, context))
+}
+
+#[tauri::command]
+pub fn list_prompt_templates(app: AppHandle) -> Result<PromptLibrary, String> {
+    load_prompt_library(&app)
+}
+
+#[tauri::command]
+pub fn create_prompt_template(
+    app: AppHandle,
+    name: String,
+    command: AiCommand,
+    body: String,
+) -> Result<PromptTemplate, String> {
+    let mut library = load_prompt_library(&app)?;
+    let template = library.create(name, command, body);
+    save_prompt_library(&app, &library)?;
+    Ok(template)
+}
+
+#[tauri::command]
+pub fn update_prompt_template(
+    app: AppHandle,
+    id: String,
+    name: Option<String>,
+    body: Option<String>,
+) -> Result<PromptTemplate, String> {
+    let mut library = load_prompt_library(&app)?;
+    let template = library.update(&id, name, body)?;
+    save_prompt_library(&app, &library)?;
+    Ok(template)
+}
+
+#[tauri::command]
+pub fn delete_prompt_template(app: AppHandle, id: String) -> Result<(), String> {
+    let mut library = load_prompt_library(&app)?;
+    library.delete(&id);
+    save_prompt_library(&app, &library)
+}
+
+// Pass no id to go back to the built-in prompt
+#[tauri::command]
+pub fn set_active_prompt_template(
+    app: AppHandle,
+    command: AiCommand,
+    id: Option<String>,
+) -> Result<(), String> {
+    let mut library = load_prompt_library(&app)?;
+    library.set_active(command, id)?;
+    save_prompt_library(&app, &library)
+}
+
+#[tauri::command]
+pub fn export_prompt_templates(
+    app: AppHandle,
+    path: String,
+    ids: Option<Vec<String>>,
+) -> Result<(), String> {
+    let library = load_prompt_library(&app)?;
+    let templates: Vec<&PromptTemplate> = library
+        .templates
+        .iter()
+        .filter(|t| match &ids {
+            Some(ids) => ids.contains(&t.id),
+            None => true,
+        })
+        .collect();
+
+    let raw = serde_json::to_string_pretty(&templates)
+        .map_err(|e| format!("Failed to serialize prompt templates: {}", e))?;
+    fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+#[tauri::command]
+pub fn import_prompt_templates(app: AppHandle, path: String) -> Result<usize, String> {
+    let raw = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let templates: Vec<PromptTemplate> = serde_json::from_str(&raw)
+        .map_err(|e| format!("{} is not a prompt template export: {}", path, e))?;
+
+    let mut library = load_prompt_library(&app)?;
+    let count = library.import(templates);
+    save_prompt_library(&app, &library)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template_variables() {
+        let context = PromptContext {
+            genre: Some("noir".to_string()),
+            file_name: Some("Chapter 3".to_string()),
+            ..Default::default()
+        };
+        let rendered = render_template(
+            "Edit this {{ genre }} excerpt from {{file_name}}.{{style_guide}} {{unclosed",
+            &context,
+        );
+        assert_eq!(
+            rendered,
+            "Edit this noir excerpt from Chapter 3. {{unclosed"
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_history() {
+        let mut library = PromptLibrary::default();
+        let template = library.create("Terse".to_string(), AiCommand::Review, "v1".to_string());
+
+        let updated = library
+            .update(&template.id, None, Some("v2".to_string()))
+            .unwrap();
+        assert_eq!(updated.version, 2);
+        assert_eq!(updated.body, "v2");
+        assert_eq!(updated.history.len(), 1);
+        assert_eq!(updated.history[0].body, "v1");
+
+        // Renaming alone is not a new version
+        let renamed = library
+            .update(&template.id, Some("Short".to_string()), None)
+            .unwrap();
+        assert_eq!(renamed.version, 2);
+    }
+
+    #[test]
+    fn test_active_template_must_match_command() {
+        let mut library = PromptLibrary::default();
+        let template = library.create("Strict".to_string(), AiCommand::Proofread, "x".to_string());
+
+        assert!(library
+            .set_active(AiCommand::Review, Some(template.id.clone()))
+            .is_err());
+        library
+            .set_active(AiCommand::Proofread, Some(template.id.clone()))
+            .unwrap();
+        assert_eq!(
+            library.active_template(AiCommand::Proofread).unwrap().id,
+            template.id
+        );
+
+        library.delete(&template.id);
+        assert!(library.active_template(AiCommand::Proofread).is_none());
+    }
+}
@@ -1,38 +1,201 @@
-use crate::ai::common::{create_client, get_api_key, OpenAIResponse};
+use tauri::AppHandle;
 
-#[tauri::command]
-pub async fn proofread_content(content: String) -> Result<OpenAIResponse, String> {
-    let api_key = get_api_key()?;
-    let client = create_client();
-
-    let system_role_content = std::env::var("PROOFREAD_SYSTEM_ROLE")
-        .unwrap_or_else(|_| "You are a professional editor. Proofread the content below and return it as clean HTML:".to_string());
-
-    let body = serde_json::json!({
-        "model": "gpt-3.5-turbo",
-        "messages": [
-            { "role": "system", "content": system_role_content },
-            { "role": "user", "content": content }
-        ]
-    });
-
-    // Send the request to the OpenAI API
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Parse the response
-    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    let proofread_content = json["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or("")
-        .to_string();
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{
+    chunk_content, prepare_chunk_prompt, prepare_chunked_prompts, run_chunked, run_prompts,
+    OpenAIResponse,
+};
+use crate::ai::edits::{
+    html_to_plain_text, merge_edits, parse_edits, ProofreadEdits, STRUCTURED_PROOFREAD_ROLE,
+};
+use crate::ai::error::AiError;
+use crate::ai::jobs::{spawn_job, spawn_job_with_id};
+use crate::ai::markup::{apply_segments, mark_segments, parse_segments, MARKUP_PROOFREAD_ROLE};
+use crate::ai::prompts::{resolve_system_prompt, PromptContext};
+use crate::ai::sanitize::merge_sanitized_html;
+use crate::ai::settings::{load_ai_settings, AiCommand};
+use crate::ai::stream::stream_html_prompts;
+
+async fn proofread(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "proofread_content", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Proofread, &context)?;
+
+    let results = run_chunked(
+        &settings,
+        AiCommand::Proofread,
+        &scope,
+        &system_role_content,
+        &content,
+    )
+    .await?;
+    let (proofread_content, report) = merge_sanitized_html(&results.parts);
 
     Ok(OpenAIResponse {
         result: proofread_content,
+        cached: results.cached,
+        sanitized: report.into_option(),
+    })
+}
+
+#[tauri::command]
+pub fn proofread_content(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> String {
+    spawn_job(
+        &app,
+        "proofread_content",
+        proofread(app.clone(), content, context),
+    )
+}
+
+async fn proofread_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(
+        &app,
+        "proofread_content_stream",
+        context.project_name.clone(),
+    )?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Proofread, &context)?;
+    let prompts = prepare_chunked_prompts(
+        &settings,
+        AiCommand::Proofread,
+        &scope,
+        &system_role_content,
+        &content,
+    )?;
+
+    stream_html_prompts(&app, &request_id, &prompts).await
+}
+
+#[tauri::command]
+pub fn proofread_content_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<String, AiError> {
+    spawn_job_with_id(
+        &app,
+        request_id.clone(),
+        "proofread_content_stream",
+        proofread_stream(app.clone(), request_id, content, context),
+    )
+}
+
+// Proofread as a list of individually acceptable edits instead of
+// rewritten HTML. Offsets refer to the plain text of `content`.
+async fn proofread_edits(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<ProofreadEdits, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let project_name = context.and_then(|c| c.project_name);
+    let scope = UsageScope::new(&app, "proofread_structured", project_name)?;
+    let text = html_to_plain_text(&content);
+
+    let chunks = chunk_content(&settings, &text);
+    let prompts = chunks
+        .iter()
+        .map(|chunk| {
+            prepare_chunk_prompt(
+                &settings,
+                AiCommand::Proofread,
+                &scope,
+                STRUCTURED_PROOFREAD_ROLE,
+                chunk,
+            )
+        })
+        .collect::<Result<Vec<_>, AiError>>()?;
+    let results = run_prompts(&prompts, settings.chunking.max_concurrency).await?;
+
+    // Anchor each chunk's edits in its own text, then shift onto the whole
+    let mut parts = Vec::new();
+    for (chunk, raw) in chunks.iter().zip(&results.parts) {
+        parts.push((
+            chunk.offset,
+            parse_edits(raw, &chunk.text).map_err(AiError::malformed)?,
+        ));
+    }
+
+    let mut edits = merge_edits(&text, parts);
+    edits.cached = results.cached;
+    Ok(edits)
+}
+
+#[tauri::command]
+pub fn proofread_structured(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> String {
+    spawn_job(
+        &app,
+        "proofread_structured",
+        proofread_edits(app.clone(), content, context),
+    )
+}
+
+// Proofread only the text runs of `content` and write the corrections back
+// into its DOM, so inline formatting and list structure are never lost
+async fn proofread_markup(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let project_name = context.and_then(|c| c.project_name);
+    let scope = UsageScope::new(&app, "proofread_preserving_markup", project_name)?;
+
+    let marked = mark_segments(&content);
+    if marked.is_empty() {
+        return Ok(OpenAIResponse {
+            result: content,
+            cached: false,
+            sanitized: None,
+        });
+    }
+
+    let results = run_chunked(
+        &settings,
+        AiCommand::Proofread,
+        &scope,
+        MARKUP_PROOFREAD_ROLE,
+        &marked,
+    )
+    .await?;
+    let corrections = parse_segments(&results.parts.join("\n"));
+
+    Ok(OpenAIResponse {
+        result: apply_segments(&content, &corrections),
+        cached: results.cached,
+        sanitized: None,
     })
 }
+
+#[tauri::command]
+pub fn proofread_preserving_markup(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> String {
+    spawn_job(
+        &app,
+        "proofread_preserving_markup",
+        proofread_markup(app.clone(), content, context),
+    )
+}
@@ -1,36 +1,78 @@
-use crate::ai::common::{create_client, get_api_key, OpenAIResponse};
+use tauri::AppHandle;
 
-#[tauri::command]
-pub async fn ai_review(content: String) -> Result<OpenAIResponse, String> {
-    let api_key = get_api_key()?;
-    let client = create_client();
-
-    let system_role_content = std::env::var("REVIEW_SYSTEM_ROLE")
-        .unwrap_or_else(|_| "You are an expert content reviewer. Provide detailed feedback on the following content:".to_string());
-
-    let body = serde_json::json!({
-        "model": "gpt-3.5-turbo",
-        "messages": [
-            { "role": "system", "content": system_role_content },
-            { "role": "user", "content": content }
-        ]
-    });
-
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    let review_feedback = json["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or("")
-        .to_string();
+use crate::ai::common::chunker::merge_reports;
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{prepare_chunked_prompts, run_chunked, OpenAIResponse};
+use crate::ai::error::AiError;
+use crate::ai::jobs::{spawn_job, spawn_job_with_id};
+use crate::ai::prompts::{resolve_system_prompt, PromptContext};
+use crate::ai::settings::{load_ai_settings, AiCommand};
+use crate::ai::stream::stream_prompts;
+
+async fn review(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "ai_review", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Review, &context)?;
+
+    let results = run_chunked(
+        &settings,
+        AiCommand::Review,
+        &scope,
+        &system_role_content,
+        &content,
+    )
+    .await?;
+    let review_feedback = merge_reports(&results.parts);
 
     Ok(OpenAIResponse {
         result: review_feedback,
+        cached: results.cached,
+        sanitized: None,
     })
 }
+
+#[tauri::command]
+pub fn ai_review(app: AppHandle, content: String, context: Option<PromptContext>) -> String {
+    spawn_job(&app, "ai_review", review(app.clone(), content, context))
+}
+
+async fn review_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "ai_review_stream", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Review, &context)?;
+    let prompts = prepare_chunked_prompts(
+        &settings,
+        AiCommand::Review,
+        &scope,
+        &system_role_content,
+        &content,
+    )?;
+
+    stream_prompts(&app, &request_id, &prompts, merge_reports).await
+}
+
+#[tauri::command]
+pub fn ai_review_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<String, AiError> {
+    spawn_job_with_id(
+        &app,
+        request_id.clone(),
+        "ai_review_stream",
+        review_stream(app.clone(), request_id, content, context),
+    )
+}
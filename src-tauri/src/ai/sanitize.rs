@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+
+use ego_tree::NodeRef;
+use scraper::node::{Element, Node};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::common::chunker::merge_html;
+use crate::ai::common::strip_code_fence;
+
+// Formats the Quill editor can represent
+const ALLOWED_TAGS: &[&str] = &[
+    "p",
+    "br",
+    "strong",
+    "b",
+    "em",
+    "i",
+    "u",
+    "s",
+    "a",
+    "img",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ol",
+    "ul",
+    "li",
+    "blockquote",
+    "pre",
+    "code",
+    "span",
+    "sub",
+    "sup",
+];
+// Dropped together with everything inside them
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "head", "title", "meta", "link", "iframe", "frame", "object", "embed",
+    "noscript", "template", "svg", "math", "form", "input", "button", "textarea", "select",
+];
+const URL_SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+// What was taken out of a model's HTML. Counts are by tag, or by
+// "tag[attribute]" for attributes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizeReport {
+    pub stripped_code_fence: bool,
+    // A full <html> document was reduced to its body
+    pub unwrapped_document: bool,
+    // Removed along with their content, e.g. script
+    pub removed_elements: BTreeMap<String, usize>,
+    // Replaced by their content, e.g. div or font
+    pub unwrapped_elements: BTreeMap<String, usize>,
+    pub removed_attributes: BTreeMap<String, usize>,
+}
+
+impl SanitizeReport {
+    pub fn is_clean(&self) -> bool {
+        *self == SanitizeReport::default()
+    }
+
+    fn absorb(&mut self, other: SanitizeReport) {
+        self.stripped_code_fence |= other.stripped_code_fence;
+        self.unwrapped_document |= other.unwrapped_document;
+        for (into, from) in [
+            (&mut self.removed_elements, other.removed_elements),
+            (&mut self.unwrapped_elements, other.unwrapped_elements),
+            (&mut self.removed_attributes, other.removed_attributes),
+        ] {
+            for (key, count) in from {
+                *into.entry(key).or_insert(0) += count;
+            }
+        }
+    }
+
+    // None when nothing was removed, for responses that carry the report
+    pub fn into_option(self) -> Option<SanitizeReport> {
+        (!self.is_clean()).then_some(self)
+    }
+}
+
+fn count(map: &mut BTreeMap<String, usize>, key: String) {
+    *map.entry(key).or_insert(0) += 1;
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+// Relative links and the listed schemes only, so no javascript: or data:
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match url.find(':') {
+        Some(colon) if !url[..colon].contains(['/', '?', '#']) => {
+            URL_SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+        }
+        _ => true,
+    }
+}
+
+// The attribute value to keep, if any. Only Quill's own `ql-` classes
+// survive a class attribute.
+fn allowed_attribute(tag: &str, name: &str, value: &str) -> Option<String> {
+    match (tag, name) {
+        (_, "class") => {
+            let classes: Vec<&str> = value
+                .split_whitespace()
+                .filter(|c| c.starts_with("ql-"))
+                .collect();
+            (!classes.is_empty()).then(|| classes.join(" "))
+        }
+        ("a", "href") => is_safe_url(value).then(|| value.to_string()),
+        // Quill embeds pasted images as data URLs
+        ("img", "src") => (is_safe_url(value)
+            || value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("data:image/"))
+        .then(|| value.to_string()),
+        ("a", "target")
+        | ("a", "rel")
+        | ("img", "alt")
+        | ("img", "width")
+        | ("img", "height")
+        | ("li", "data-list")
+        | ("pre", "spellcheck") => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn write_element(element: &Element, out: &mut String, report: &mut SanitizeReport) {
+    let tag = element.name();
+    out.push('<');
+    out.push_str(tag);
+    // Sorted, since the parser does not keep attributes in source order
+    let mut attrs: Vec<(&str, &str)> = element.attrs().collect();
+    attrs.sort();
+    for (name, value) in attrs {
+        match allowed_attribute(tag, name, value) {
+            Some(kept) => {
+                out.push(' ');
+                out.push_str(name);
+                out.push_str("=\"");
+                escape_attribute(&kept, out);
+                out.push('"');
+                if kept != value {
+                    count(&mut report.removed_attributes, format!("{}[{}]", tag, name));
+                }
+            }
+            None => count(&mut report.removed_attributes, format!("{}[{}]", tag, name)),
+        }
+    }
+    out.push('>');
+}
+
+fn write_children(node: &NodeRef<'_, Node>, out: &mut String, report: &mut SanitizeReport) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => escape_text(&text.text, out),
+            Node::Comment(_) => count(&mut report.removed_elements, "#comment".to_string()),
+            Node::Element(element) => {
+                let tag = element.name();
+                if DROPPED_TAGS.contains(&tag) {
+                    count(&mut report.removed_elements, tag.to_string());
+                } else if !ALLOWED_TAGS.contains(&tag) {
+                    count(&mut report.unwrapped_elements, tag.to_string());
+                    write_children(&child, out, report);
+                } else if tag == "br" || tag == "img" {
+                    write_element(element, out, report);
+                } else {
+                    write_element(element, out, report);
+                    write_children(&child, out, report);
+                    out.push_str("</");
+                    out.push_str(tag);
+                    out.push('>');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_document(html: &str) -> bool {
+    let lower = html.trim_start().to_ascii_lowercase();
+    lower.starts_with("<!doctype") || lower.contains("<html") || lower.contains("<body")
+}
+
+// Reduce a model's response to Quill-compatible HTML: no code fences, no
+// document wrapper, and only allowlisted tags and attributes
+pub fn sanitize_html(raw: &str) -> (String, SanitizeReport) {
+    let mut report = SanitizeReport::default();
+    let html = strip_code_fence(raw);
+    report.stripped_code_fence = html.len() != raw.trim().len();
+
+    let mut out = String::new();
+    if is_document(html) {
+        report.unwrapped_document = true;
+        let document = Html::parse_document(html);
+        for child in document.root_element().children() {
+            if let Node::Element(element) = child.value() {
+                match element.name() {
+                    "body" => write_children(&child, &mut out, &mut report),
+                    // Head content (title, style, meta) never belongs in a chapter
+                    "head" => {
+                        for item in child.children() {
+                            if let Node::Element(element) = item.value() {
+                                count(&mut report.removed_elements, element.name().to_string());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    } else {
+        let fragment = Html::parse_fragment(html);
+        write_children(&fragment.root_element(), &mut out, &mut report);
+    }
+
+    (out.trim().to_string(), report)
+}
+
+// `merge_html` over sanitized parts, with one report for all of them
+pub fn merge_sanitized_html(parts: &[String]) -> (String, SanitizeReport) {
+    let mut report = SanitizeReport::default();
+    let mut clean = Vec::new();
+    for part in parts {
+        let (html, part_report) = sanitize_html(part);
+        report.absorb(part_report);
+        clean.push(html);
+    }
+    (merge_html(&clean), report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_quill_html_is_unchanged() {
+        let html = "<p>A <strong>bold</strong> &amp; <em>brave</em> line.<br></p>\
+            <ol><li class=\"ql-indent-1\" data-list=\"bullet\">One</li></ol>\
+            <p><a href=\"https://example.com\" target=\"_blank\">Link</a>\
+            <img alt=\"Map\" src=\"data:image/png;base64,AAAA\"></p>";
+        let (clean, report) = sanitize_html(html);
+        assert_eq!(clean, html);
+        assert!(report.is_clean());
+        assert_eq!(report.into_option(), None);
+    }
+
+    #[test]
+    fn test_fenced_document_is_unwrapped() {
+        let raw = "```html\n<!DOCTYPE html><html><head><title>Ch 1</title>\
+            <style>p { color: red }</style></head>\
+            <body><div><p style=\"color: red\" onclick=\"x()\">Fixed.</p></div>\
+            <script>alert(1)</script></body></html>\n```";
+        let (clean, report) = sanitize_html(raw);
+
+        assert_eq!(clean, "<p>Fixed.</p>");
+        assert!(report.stripped_code_fence);
+        assert!(report.unwrapped_document);
+        assert_eq!(report.removed_elements.get("title"), Some(&1));
+        assert_eq!(report.removed_elements.get("style"), Some(&1));
+        assert_eq!(report.removed_elements.get("script"), Some(&1));
+        assert_eq!(report.unwrapped_elements.get("div"), Some(&1));
+        assert_eq!(report.removed_attributes.get("p[style]"), Some(&1));
+        assert_eq!(report.removed_attributes.get("p[onclick]"), Some(&1));
+    }
+
+    #[test]
+    fn test_unsafe_links_and_classes_are_removed() {
+        let html = "<p class=\"ql-align-center shout\"><a href=\" JavaScript:alert(1)\">x</a>\
+            <a href=\"notes/ch2.html#top\">y</a><font>z</font></p><!-- note -->";
+        let (clean, report) = sanitize_html(html);
+
+        assert_eq!(
+            clean,
+            "<p class=\"ql-align-center\"><a>x</a><a href=\"notes/ch2.html#top\">y</a>z</p>"
+        );
+        assert_eq!(report.removed_attributes.get("a[href]"), Some(&1));
+        assert_eq!(report.removed_attributes.get("p[class]"), Some(&1));
+        assert_eq!(report.unwrapped_elements.get("font"), Some(&1));
+        assert_eq!(report.removed_elements.get("#comment"), Some(&1));
+    }
+
+    #[test]
+    fn test_merge_reports_every_part() {
+        let parts = vec![
+            "```\n<p>One</p>\n```".to_string(),
+            "<p>Two</p><script>x</script>".to_string(),
+        ];
+        let (html, report) = merge_sanitized_html(&parts);
+        assert_eq!(html, "<p>One</p><p>Two</p>");
+        assert!(report.stripped_code_fence);
+        assert_eq!(report.removed_elements.get("script"), Some(&1));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::ai::chat::ChatSettings;
+use crate::ai::common::budget::BudgetSettings;
+use crate::ai::common::cache::CacheSettings;
+use crate::ai::common::chunker::ChunkSettings;
+use crate::ai::common::retry::RetrySettings;
+use crate::ai::common::usage::ModelPrice;
+
+const AI_SETTINGS_FILE: &str = "ai_settings.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    OpenAi,
+    Anthropic,
+    Ollama,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiCommand {
+    Proofread,
+    Suggestions,
+    Review,
+}
+
+// Model parameters for a request. Unset fields fall back to the shared
+// defaults, then to the provider's own defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelParams {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    pub timeout_secs: Option<u64>,
+}
+
+impl ModelParams {
+    pub fn or(&self, fallback: &ModelParams) -> ModelParams {
+        ModelParams {
+            model: self.model.clone().or_else(|| fallback.model.clone()),
+            temperature: self.temperature.or(fallback.temperature),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            top_p: self.top_p.or(fallback.top_p),
+            timeout_secs: self.timeout_secs.or(fallback.timeout_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiSettings {
+    pub provider: ProviderKind,
+    // Overrides the provider's default endpoint, e.g. a llama.cpp server
+    pub base_url: Option<String>,
+    // Shared by every command unless overridden below
+    pub defaults: ModelParams,
+    pub proofread: ModelParams,
+    pub suggestions: ModelParams,
+    pub review: ModelParams,
+    pub chunking: ChunkSettings,
+    pub retry: RetrySettings,
+    // Prices by model name, on top of the built-in table
+    pub prices: HashMap<String, ModelPrice>,
+    pub budget: BudgetSettings,
+    pub cache: CacheSettings,
+    pub chat: ChatSettings,
+}
+
+impl AiSettings {
+    fn command_params(&self, command: AiCommand) -> &ModelParams {
+        match command {
+            AiCommand::Proofread => &self.proofread,
+            AiCommand::Suggestions => &self.suggestions,
+            AiCommand::Review => &self.review,
+        }
+    }
+
+    fn command_params_mut(&mut self, command: AiCommand) -> &mut ModelParams {
+        match command {
+            AiCommand::Proofread => &mut self.proofread,
+            AiCommand::Suggestions => &mut self.suggestions,
+            AiCommand::Review => &mut self.review,
+        }
+    }
+
+    // Resolved parameters for one command
+    pub fn params_for(&self, command: AiCommand) -> ModelParams {
+        self.command_params(command).or(&self.defaults)
+    }
+}
+
+// Path of a file in the app data `User` directory, next to the frontend's
+// settings.json
+pub fn user_data_path(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+
+    Ok(app_data_dir.join("User").join(file_name))
+}
+
+pub fn load_ai_settings(app: &AppHandle) -> Result<AiSettings, String> {
+    let path = user_data_path(app, AI_SETTINGS_FILE)?;
+    if !path.exists() {
+        return Ok(AiSettings::default());
+    }
+
+    let raw =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read AI settings: {}", e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse AI settings: {}", e))
+}
+
+pub fn save_ai_settings_to_disk(app: &AppHandle, settings: &AiSettings) -> Result<(), String> {
+    let path = user_data_path(app, AI_SETTINGS_FILE)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+
+    let raw = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize AI settings: {}", e))?;
+    fs::write(&path, raw).map_err(|e| format!("Failed to write AI settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_ai_settings(app: AppHandle) -> Result<AiSettings, String> {
+    load_ai_settings(&app)
+}
+
+#[tauri::command]
+pub fn save_ai_settings(app: AppHandle, settings: AiSettings) -> Result<(), String> {
+    save_ai_settings_to_disk(&app, &settings)
+}
+
+#[tauri::command]
+pub fn get_ai_command_config(app: AppHandle, command: AiCommand) -> Result<ModelParams, String> {
+    Ok(load_ai_settings(&app)?.params_for(command))
+}
+
+#[tauri::command]
+pub fn set_ai_command_config(
+    app: AppHandle,
+    command: AiCommand,
+    params: ModelParams,
+) -> Result<(), String> {
+    let mut settings = load_ai_settings(&app)?;
+    *settings.command_params_mut(command) = params;
+    save_ai_settings_to_disk(&app, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_params_fall_back_to_defaults() {
+        let settings = AiSettings {
+            defaults: ModelParams {
+                model: Some("gpt-4o-mini".to_string()),
+                temperature: Some(0.2),
+                ..Default::default()
+            },
+            review: ModelParams {
+                model: Some("gpt-4o".to_string()),
+                max_tokens: Some(2000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let review = settings.params_for(AiCommand::Review);
+        assert_eq!(review.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(review.temperature, Some(0.2));
+        assert_eq!(review.max_tokens, Some(2000));
+
+        let proofread = settings.params_for(AiCommand::Proofread);
+        assert_eq!(proofread.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(proofread.max_tokens, None);
+    }
+
+    #[test]
+    fn test_missing_fields_deserialize_to_defaults() {
+        let settings: AiSettings =
+            serde_json::from_str(r#"{ "provider": "ollama", "review": { "top_p": 0.9 } }"#)
+                .unwrap();
+        assert_eq!(settings.provider, ProviderKind::Ollama);
+        assert_eq!(settings.review.top_p, Some(0.9));
+        assert_eq!(settings.proofread, ModelParams::default());
+    }
+}
@@ -0,0 +1,354 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::ai::common::cache::{cached_response, store_response};
+use crate::ai::common::usage::{record_usage, TokenUsage};
+use crate::ai::common::{
+    create_client, send_with_retry, Completion, OpenAIResponse, PreparedPrompt, PromptResults,
+    StreamDelta,
+};
+use crate::ai::error::AiError;
+use crate::ai::sanitize::merge_sanitized_html;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiStreamKind {
+    Delta,
+    Done,
+    Error,
+}
+
+// Payload of the `ai-stream` event. `text` holds the new fragment for
+// deltas, the full result when done, and the message on error. Deltas are
+// always plain text and must not be rendered as HTML.
+#[derive(Debug, Clone, Serialize)]
+pub struct AiStreamEvent {
+    pub request_id: String,
+    pub kind: AiStreamKind,
+    pub text: String,
+}
+
+fn emit_stream(app: &AppHandle, request_id: &str, kind: AiStreamKind, text: &str) {
+    let _ = app.emit(
+        "ai-stream",
+        AiStreamEvent {
+            request_id: request_id.to_string(),
+            kind,
+            text: text.to_string(),
+        },
+    );
+}
+
+// Incrementally splits a server-sent event stream into `data:` payloads
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data_lines: Vec<String>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if line.is_empty() {
+                // A blank line terminates the current event
+                if !self.data_lines.is_empty() {
+                    events.push(self.data_lines.join("\n"));
+                    self.data_lines.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data_lines
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+        }
+
+        events
+    }
+
+    // Flush an event left unterminated when the connection closed
+    pub fn finish(&mut self) -> Option<String> {
+        let mut events = self.push(b"\n");
+        if !self.data_lines.is_empty() {
+            events.push(self.data_lines.join("\n"));
+            self.data_lines.clear();
+        }
+        events.pop()
+    }
+}
+
+// Incrementally converts streamed HTML into plain text, holding back a tag
+// or entity split across fragments until it is complete
+#[derive(Default)]
+pub struct HtmlTextStream {
+    pending: String,
+}
+
+impl HtmlTextStream {
+    pub fn push(&mut self, html: &str) -> String {
+        self.pending.push_str(html);
+        let mut text = String::new();
+        let mut rest = self.pending.as_str();
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => break,
+                };
+                let tag = rest[1..end].trim().to_ascii_lowercase();
+                let name = tag.trim_start_matches('/').split([' ', '/']).next();
+                let closes_block = tag.starts_with('/')
+                    && matches!(
+                        name,
+                        Some("p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li" | "blockquote")
+                    );
+                if closes_block || name == Some("br") {
+                    text.push('\n');
+                }
+                rest = &rest[end + 1..];
+            } else if c == '&' {
+                match rest.find(';').filter(|end| *end <= 8) {
+                    Some(end) => {
+                        text.push_str(decode_entity(&rest[1..end]).unwrap_or(&rest[..=end]));
+                        rest = &rest[end + 1..];
+                    }
+                    // Wait for the rest of what may be an entity
+                    None if rest.len() < 8 => break,
+                    None => {
+                        text.push('&');
+                        rest = &rest[1..];
+                    }
+                }
+            } else {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        self.pending = rest.to_string();
+        text
+    }
+}
+
+fn decode_entity(name: &str) -> Option<&'static str> {
+    match name {
+        "amp" => Some("&"),
+        "lt" => Some("<"),
+        "gt" => Some(">"),
+        "quot" => Some("\""),
+        "apos" | "#39" => Some("'"),
+        "nbsp" => Some("\u{a0}"),
+        _ => None,
+    }
+}
+
+// Emits deltas for one response, as plain text when `html` is set
+struct DeltaEmitter<'a> {
+    app: &'a AppHandle,
+    request_id: &'a str,
+    html: Option<HtmlTextStream>,
+}
+
+impl DeltaEmitter<'_> {
+    fn emit(&mut self, text: &str) {
+        let text = match &mut self.html {
+            Some(html) => html.push(text),
+            None => text.to_string(),
+        };
+        if !text.is_empty() {
+            emit_stream(self.app, self.request_id, AiStreamKind::Delta, &text);
+        }
+    }
+}
+
+async fn read_stream(
+    app: &AppHandle,
+    request_id: &str,
+    prompt: &PreparedPrompt,
+    html: bool,
+) -> Result<Completion, AiError> {
+    let mut deltas = DeltaEmitter {
+        app,
+        request_id,
+        html: html.then(HtmlTextStream::default),
+    };
+
+    // A cached response arrives as a single delta
+    if let Some(text) = cached_response(prompt) {
+        let text = prompt.restore(&text);
+        deltas.emit(&text);
+        return Ok(Completion { text, cached: true });
+    }
+
+    let client = create_client();
+    let provider = prompt.provider.as_ref();
+
+    // Only the connection is retried; once text has been emitted a retry
+    // would duplicate it
+    let mut response = send_with_retry(&client, prompt, true).await?;
+
+    let mut parser = SseParser::default();
+    let mut result = String::new();
+    let mut usage: Option<TokenUsage> = None;
+    let mut done = false;
+    // Redacted text not yet emitted because it may end in part of a
+    // placeholder
+    let mut pending = String::new();
+
+    let mut apply = |delta: StreamDelta, result: &mut String| match delta {
+        StreamDelta::Text(text) => {
+            let restored = match &prompt.redactor {
+                Some(redactor) => redactor.restore_partial(&mut pending, &text),
+                None => text.clone(),
+            };
+            deltas.emit(&restored);
+            result.push_str(&text);
+            false
+        }
+        StreamDelta::Usage(counts) => {
+            usage.get_or_insert_with(TokenUsage::default).absorb(counts);
+            false
+        }
+        StreamDelta::Done => true,
+        StreamDelta::Skip => false,
+    };
+
+    while !done {
+        let chunk = match response.chunk().await.map_err(AiError::from_reqwest)? {
+            Some(chunk) => chunk,
+            None => break,
+        };
+        for data in parser.push(&chunk) {
+            if apply(provider.parse_stream_data(&data)?, &mut result) {
+                done = true;
+                break;
+            }
+        }
+    }
+
+    if !done {
+        if let Some(data) = parser.finish() {
+            done = apply(provider.parse_stream_data(&data)?, &mut result);
+        }
+    }
+
+    record_usage(prompt, usage, &result);
+    // A connection dropped mid-response leaves the text cut short
+    if !done {
+        return Err(AiError::malformed(
+            "The response stream ended before the provider finished",
+        ));
+    }
+
+    if !pending.is_empty() {
+        deltas.emit(&prompt.restore(&pending));
+    }
+
+    store_response(prompt, &result);
+    Ok(Completion {
+        text: prompt.restore(&result),
+        cached: false,
+    })
+}
+
+// Stream prepared prompts one after another, emitting deltas as text
+// arrives, or an `error` event if one fails
+async fn read_streams(
+    app: &AppHandle,
+    request_id: &str,
+    prompts: &[PreparedPrompt],
+    html: bool,
+) -> Result<PromptResults, AiError> {
+    let mut completions: Vec<Completion> = Vec::new();
+
+    for prompt in prompts {
+        match read_stream(app, request_id, prompt, html).await {
+            Ok(completion) => completions.push(completion),
+            Err(e) => {
+                emit_stream(app, request_id, AiStreamKind::Error, &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(completions.into_iter().collect())
+}
+
+// Stream prepared prompts, emitting `ai-stream` events as text arrives.
+// Always finishes with a `done` event carrying the merged result, or an
+// `error` event, for the request id.
+pub async fn stream_prompts(
+    app: &AppHandle,
+    request_id: &str,
+    prompts: &[PreparedPrompt],
+    merge: fn(&[String]) -> String,
+) -> Result<OpenAIResponse, AiError> {
+    let results = read_streams(app, request_id, prompts, false).await?;
+    let result = merge(&results.parts);
+    emit_stream(app, request_id, AiStreamKind::Done, &result);
+    Ok(OpenAIResponse {
+        result,
+        cached: results.cached,
+        sanitized: None,
+    })
+}
+
+// As `stream_prompts` for HTML. Deltas are the model output with markup
+// stripped, as a plain-text preview; only the `done` event carries HTML,
+// and it is sanitized.
+pub async fn stream_html_prompts(
+    app: &AppHandle,
+    request_id: &str,
+    prompts: &[PreparedPrompt],
+) -> Result<OpenAIResponse, AiError> {
+    let results = read_streams(app, request_id, prompts, true).await?;
+    let (result, report) = merge_sanitized_html(&results.parts);
+    emit_stream(app, request_id, AiStreamKind::Done, &result);
+    Ok(OpenAIResponse {
+        result,
+        cached: results.cached,
+        sanitized: report.into_option(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"a\":").is_empty());
+        let events = parser.push(b"1}\n\ndata: [DONE]\n\n");
+        assert_eq!(events, vec!["{\"a\":1}".to_string(), "[DONE]".to_string()]);
+    }
+
+    #[test]
+    fn test_sse_ignores_event_names_and_crlf() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b"event: ping\r\ndata: {}\r\n\r\n");
+        assert_eq!(events, vec!["{}".to_string()]);
+    }
+
+    #[test]
+    fn test_sse_finish_flushes_trailing_event() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: tail").is_empty());
+        assert_eq!(parser.finish(), Some("tail".to_string()));
+    }
+
+    #[test]
+    fn test_html_deltas_become_plain_text() {
+        let mut stream = HtmlTextStream::default();
+        assert_eq!(stream.push("<p>Fish &am"), "Fish ");
+        assert_eq!(stream.push("p; chips</p><p><img src=x onerr"), "& chips\n");
+        assert_eq!(stream.push("or=alert(1)>Next<br>line"), "Next\nline");
+        assert_eq!(stream.push(" 5 &lt; 6 &copy;</p>"), " 5 < 6 &copy;\n");
+    }
+}
@@ -1,37 +1,83 @@
-use crate::ai::common::{create_client, get_api_key, OpenAIResponse};
+use tauri::AppHandle;
+
+use crate::ai::common::chunker::merge_lists;
+use crate::ai::common::usage::UsageScope;
+use crate::ai::common::{prepare_chunked_prompts, run_chunked, OpenAIResponse};
+use crate::ai::error::AiError;
+use crate::ai::jobs::{spawn_job, spawn_job_with_id};
+use crate::ai::prompts::{resolve_system_prompt, PromptContext};
+use crate::ai::settings::{load_ai_settings, AiCommand};
+use crate::ai::stream::stream_prompts;
 // AI Suggestions
 
-#[tauri::command]
-pub async fn ai_suggestions(content: String) -> Result<OpenAIResponse, String> {
-    let api_key = get_api_key()?;
-    let client = create_client();
-
-    let system_role_content = std::env::var("SUGGESTIONS_SYSTEM_ROLE")
-        .unwrap_or_else(|_| "You are an expert writing assistant. Provide suggestions for improvement to the following content:".to_string());
-
-    let body = serde_json::json!({
-        "model": "gpt-3.5-turbo",
-        "messages": [
-            { "role": "system", "content": system_role_content },
-            { "role": "user", "content": content }
-        ]
-    });
-
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    let suggestions = json["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or("")
-        .to_string();
+async fn suggestions(
+    app: AppHandle,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "ai_suggestions", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Suggestions, &context)?;
+
+    let results = run_chunked(
+        &settings,
+        AiCommand::Suggestions,
+        &scope,
+        &system_role_content,
+        &content,
+    )
+    .await?;
+    let suggestions = merge_lists(&results.parts);
 
     Ok(OpenAIResponse {
         result: suggestions,
+        cached: results.cached,
+        sanitized: None,
     })
 }
+
+#[tauri::command]
+pub fn ai_suggestions(app: AppHandle, content: String, context: Option<PromptContext>) -> String {
+    spawn_job(
+        &app,
+        "ai_suggestions",
+        suggestions(app.clone(), content, context),
+    )
+}
+
+async fn suggestions_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<OpenAIResponse, AiError> {
+    let settings = load_ai_settings(&app)?;
+    let context = context.unwrap_or_default();
+    let scope = UsageScope::new(&app, "ai_suggestions_stream", context.project_name.clone())?;
+    let system_role_content = resolve_system_prompt(&app, AiCommand::Suggestions, &context)?;
+    let prompts = prepare_chunked_prompts(
+        &settings,
+        AiCommand::Suggestions,
+        &scope,
+        &system_role_content,
+        &content,
+    )?;
+
+    stream_prompts(&app, &request_id, &prompts, merge_lists).await
+}
+
+#[tauri::command]
+pub fn ai_suggestions_stream(
+    app: AppHandle,
+    request_id: String,
+    content: String,
+    context: Option<PromptContext>,
+) -> Result<String, AiError> {
+    spawn_job_with_id(
+        &app,
+        request_id.clone(),
+        "ai_suggestions_stream",
+        suggestions_stream(app.clone(), request_id, content, context),
+    )
+}
@@ -3,12 +3,14 @@ use ego_tree::NodeRef;
 use scraper::node::Node;
 use scraper::Html;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CompiledDocument {
     pub title: String,
     pub author: String,
     pub front_matter: Option<String>,
     pub back_matter: Option<String>,
+    pub language: Option<String>,
+    pub identifier: Option<String>,
     pub chapters: Vec<Chapter>,
 }
 
@@ -37,13 +39,54 @@ pub enum BlockType {
     Paragraph,
     ParagraphBreak,
     ListItem,
+    // Ends a list item, whose inline runs come before it
+    ListItemBreak,
     Heading,
 }
 
 pub fn compile(payload: &ExportPayload) -> Result<CompiledDocument, String> {
     let opts = &payload.options;
-    let nodes = &payload.nodes;
 
+    Ok(CompiledDocument {
+        title: opts.title.clone(),
+        author: opts.author.clone(),
+        front_matter: opts.front_matter.clone(),
+        back_matter: opts.back_matter.clone(),
+        language: opts.language.clone(),
+        identifier: opts.identifier.clone(),
+        chapters: compile_chapters(&payload.nodes),
+    })
+}
+
+impl CompiledDocument {
+    // The language tag, if one was given
+    pub fn language_tag(&self) -> Option<&str> {
+        self.language
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+    }
+
+    // Ebook and web formats must declare a language; untagged books are
+    // taken to be English
+    pub fn language_or_default(&self) -> &str {
+        self.language_tag().unwrap_or("en")
+    }
+}
+
+// Front and back matter are plain text; each non-blank line becomes a
+// paragraph
+pub fn matter_paragraphs(text: Option<&str>) -> Vec<&str> {
+    text.unwrap_or("")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+// Arrange the project tree into chapters: top-level folders become named
+// chapters, top-level files become auto-numbered ones
+pub fn compile_chapters(nodes: &[ExportFileNode]) -> Vec<Chapter> {
     let mut chapters: Vec<Chapter> = Vec::new();
 
     // Separate top-level folders and top-level files
@@ -111,22 +154,13 @@ pub fn compile(payload: &ExportPayload) -> Result<CompiledDocument, String> {
             title: if top_level_folders.is_empty() && top_level_files.len() == 1 {
                 file.text.clone()
             } else {
-                format!(
-                    "Chapter {}",
-                    chapters.len() + i + 1 - top_level_folders.len()
-                )
+                format!("Chapter {}", i + 1)
             },
             sections: vec![section],
         });
     }
 
-    Ok(CompiledDocument {
-        title: opts.title.clone(),
-        author: opts.author.clone(),
-        front_matter: opts.front_matter.clone(),
-        back_matter: opts.back_matter.clone(),
-        chapters,
-    })
+    chapters
 }
 
 fn parse_html_content(html: &str) -> Vec<TextElement> {
@@ -187,19 +221,31 @@ fn parse_list_node(node: &NodeRef<'_, Node>) -> Vec<TextElement> {
 
     for child in node.children() {
         let child_ref: NodeRef<'_, Node> = child;
-        match child_ref.value() {
-            Node::Element(el) => {
-                let tag = el.name();
-                if tag == "li" {
-                    let inline =
-                        extract_inline_elements(&child_ref, false, false, &BlockType::ListItem);
-                    elements.extend(inline);
-                } else if tag == "ol" || tag == "ul" {
-                    let nested = parse_list_node(&child_ref);
-                    elements.extend(nested);
+        if let Node::Element(el) = child_ref.value() {
+            let tag = el.name();
+            if tag == "li" {
+                let inline =
+                    extract_inline_elements(&child_ref, false, false, &BlockType::ListItem);
+                elements.extend(inline);
+                elements.push(TextElement {
+                    text: String::new(),
+                    bold: false,
+                    italic: false,
+                    block_type: BlockType::ListItemBreak,
+                });
+
+                // Nested lists follow as items of their own
+                for nested in child_ref.children() {
+                    if let Node::Element(el) = nested.value() {
+                        if el.name() == "ol" || el.name() == "ul" {
+                            elements.extend(parse_list_node(&nested));
+                        }
+                    }
                 }
+            } else if tag == "ol" || tag == "ul" {
+                let nested = parse_list_node(&child_ref);
+                elements.extend(nested);
             }
-            _ => {}
         }
     }
 
@@ -234,6 +280,8 @@ fn extract_inline_elements(
                     "strong" | "b" => (true, italic),
                     "em" | "i" => (bold, true),
                     "u" | "s" | "del" | "strike" => (bold, italic),
+                    // Taken separately by `parse_list_node`
+                    "ol" | "ul" if *block_type == BlockType::ListItem => continue,
                     "br" => {
                         elements.push(TextElement {
                             text: "\n".to_string(),
@@ -255,10 +303,107 @@ fn extract_inline_elements(
     elements
 }
 
+// A run of elements that renders as one block. Adapters other than the PDF
+// one work on these rather than on the flat element list.
+#[derive(Debug, Clone)]
+pub enum Block<'a> {
+    Paragraph(Vec<&'a TextElement>),
+    Heading(Vec<&'a TextElement>),
+    // The inline runs of each item
+    List(Vec<Vec<&'a TextElement>>),
+}
+
+// Group a section's elements into blocks. Inline runs stay together until a
+// ParagraphBreak, or a ListItemBreak within a list; consecutive list items
+// form one list.
+pub fn group_blocks(elements: &[TextElement]) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    // The last item of the current list is still taking runs
+    let mut item_open = false;
+
+    for element in elements {
+        match element.block_type {
+            BlockType::ParagraphBreak => {
+                blocks.extend(current.take());
+            }
+            BlockType::ListItemBreak => item_open = false,
+            BlockType::ListItem => {
+                match current {
+                    Some(Block::List(ref mut items)) => match items.last_mut() {
+                        Some(item) if item_open => item.push(element),
+                        _ => items.push(vec![element]),
+                    },
+                    _ => {
+                        blocks.extend(current.take());
+                        current = Some(Block::List(vec![vec![element]]));
+                    }
+                }
+                item_open = true;
+            }
+            BlockType::Paragraph => match current {
+                Some(Block::Paragraph(ref mut runs)) => runs.push(element),
+                _ => {
+                    blocks.extend(current.take());
+                    current = Some(Block::Paragraph(vec![element]));
+                }
+            },
+            BlockType::Heading => match current {
+                Some(Block::Heading(ref mut runs)) => runs.push(element),
+                _ => {
+                    blocks.extend(current.take());
+                    current = Some(Block::Heading(vec![element]));
+                }
+            },
+        }
+    }
+    blocks.extend(current);
+
+    blocks
+}
+
+// Authors mark scene breaks with a paragraph of symbols such as "* * *",
+// "***", "#" or "~~~"
+pub fn is_scene_break(runs: &[&TextElement]) -> bool {
+    let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+    let text = text.trim();
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| matches!(c, '*' | '#' | '~' | '-' | '•' | '⁂') || c.is_whitespace())
+}
+
+// Fixtures shared by the exporters' tests
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    // A file node, or a folder node if `content` is None
+    pub fn node(id: i64, parent: i64, text: &str, content: Option<&str>) -> ExportFileNode {
+        ExportFileNode {
+            id,
+            parent,
+            text: text.to_string(),
+            file_type: if content.is_some() { "file" } else { "folder" }.to_string(),
+            content: content.map(|c| c.to_string()),
+        }
+    }
+
+    // "Tom & Jerry" by "Test Author", compiled from `nodes`
+    pub fn document(nodes: &[ExportFileNode]) -> CompiledDocument {
+        CompiledDocument {
+            title: "Tom & Jerry".to_string(),
+            author: "Test Author".to_string(),
+            chapters: compile_chapters(nodes),
+            ..Default::default()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::export::types::{ExportFileNode, ExportOptions, ExportPayload};
+    use crate::export::types::{ExportFileNode, ExportOptions, ExportPayload, FormatOptions};
 
     fn make_payload(nodes: Vec<ExportFileNode>) -> ExportPayload {
         ExportPayload {
@@ -269,7 +414,11 @@ mod tests {
                 author: "Test Author".to_string(),
                 front_matter: None,
                 back_matter: None,
+                language: None,
+                identifier: None,
             },
+            format: "pdf".to_string(),
+            format_options: FormatOptions::new(),
         }
     }
 
@@ -363,9 +512,32 @@ mod tests {
     #[test]
     fn test_list_items() {
         let elements = parse_html_content("<ul><li>Item one</li><li>Item two</li></ul>");
-        assert_eq!(elements.len(), 2);
+        // Each item's run followed by a ListItemBreak
+        assert_eq!(elements.len(), 4);
         assert_eq!(elements[0].block_type, BlockType::ListItem);
-        assert_eq!(elements[1].block_type, BlockType::ListItem);
+        assert_eq!(elements[1].block_type, BlockType::ListItemBreak);
+        assert_eq!(elements[2].block_type, BlockType::ListItem);
+        assert_eq!(elements[3].block_type, BlockType::ListItemBreak);
+    }
+
+    #[test]
+    fn test_list_item_keeps_its_runs_together() {
+        let elements = parse_html_content(
+            "<ul><li>Buy <strong>milk</strong></li><li>Call <em>Mum</em><ul><li>Soon</li></ul></li></ul>",
+        );
+        let blocks = group_blocks(&elements);
+        assert_eq!(blocks.len(), 1);
+        let items = match &blocks[0] {
+            Block::List(items) => items,
+            other => panic!("expected a list, got {:?}", other),
+        };
+        let texts: Vec<String> = items
+            .iter()
+            .map(|item| item.iter().map(|r| r.text.as_str()).collect())
+            .collect();
+        assert_eq!(texts, vec!["Buy milk", "Call Mum", "Soon"]);
+        assert!(items[0][1].bold);
+        assert!(items[1][1].italic);
     }
 
     #[test]
@@ -406,4 +578,53 @@ mod tests {
         assert!(!elements[2].bold);
         assert_eq!(elements[3].block_type, BlockType::ParagraphBreak);
     }
+
+    #[test]
+    fn test_group_blocks() {
+        let elements = parse_html_content(
+            "<h2>Title</h2><p>Some <em>words</em></p><ul><li>One</li><li>Two</li></ul><p>End</p>",
+        );
+        let blocks = group_blocks(&elements);
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(&blocks[0], Block::Heading(runs) if runs[0].text == "Title"));
+        assert!(matches!(&blocks[1], Block::Paragraph(runs) if runs.len() == 2 && runs[1].italic));
+        assert!(
+            matches!(&blocks[2], Block::List(items) if items.len() == 2 && items[1][0].text == "Two")
+        );
+        assert!(matches!(&blocks[3], Block::Paragraph(runs) if runs[0].text == "End"));
+    }
+
+    #[test]
+    fn test_matter_and_language_defaults() {
+        assert_eq!(
+            matter_paragraphs(Some("  For Ada\n\n  and Tom  \n")),
+            vec!["For Ada", "and Tom"]
+        );
+        assert!(matter_paragraphs(None).is_empty());
+
+        let mut doc = CompiledDocument {
+            language: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(doc.language_tag(), None);
+        assert_eq!(doc.language_or_default(), "en");
+        doc.language = Some(" fr-CA ".to_string());
+        assert_eq!(doc.language_or_default(), "fr-CA");
+    }
+
+    #[test]
+    fn test_scene_breaks() {
+        for html in ["<p>* * *</p>", "<p>#</p>", "<p><em>~~~</em></p>"] {
+            let elements = parse_html_content(html);
+            assert!(
+                matches!(&group_blocks(&elements)[0], Block::Paragraph(runs) if is_scene_break(runs)),
+                "{}",
+                html
+            );
+        }
+        let elements = parse_html_content("<p>* not a break</p>");
+        assert!(
+            matches!(&group_blocks(&elements)[0], Block::Paragraph(runs) if !is_scene_break(runs))
+        );
+    }
 }
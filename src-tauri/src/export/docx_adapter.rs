@@ -0,0 +1,458 @@
+use std::fs::{self, File};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::export::compiler::{
+    group_blocks, matter_paragraphs, Block, Chapter, CompiledDocument, TextElement,
+};
+use crate::export::exporter::Exporter;
+use crate::export::types::FormatOptions;
+use crate::export::{escape_xml, output_file_name};
+
+const WORD_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+// Bullets for list items; numId 1 in document.xml refers to this
+const BULLET_NUM_ID: u32 = 1;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="xml" ContentType="application/xml"/>
+  <Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+  <Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+  <Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/>
+  <Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+</Types>
+"#;
+
+const PACKAGE_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering" Target="numbering.xml"/>
+</Relationships>
+"#;
+
+const NUMBERING: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:numbering xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:abstractNum w:abstractNumId="0">
+    <w:multiLevelType w:val="singleLevel"/>
+    <w:lvl w:ilvl="0">
+      <w:start w:val="1"/>
+      <w:numFmt w:val="bullet"/>
+      <w:lvlText w:val="•"/>
+      <w:lvlJc w:val="left"/>
+      <w:pPr><w:ind w:left="720" w:hanging="360"/></w:pPr>
+    </w:lvl>
+  </w:abstractNum>
+  <w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>
+</w:numbering>
+"#;
+
+// Letter size, one-inch margins (in twentieths of a point)
+const SECTION_PROPERTIES: &str = r#"<w:sectPr><w:pgSz w:w="12240" w:h="15840"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="720" w:footer="720" w:gutter="0"/></w:sectPr>"#;
+
+// How a paragraph is laid out, beyond its runs
+#[derive(Default)]
+struct ParagraphProps<'a> {
+    style: Option<&'a str>,
+    page_break_before: bool,
+    bullet: bool,
+}
+
+pub struct DocxExporter;
+
+impl Exporter for DocxExporter {
+    fn id(&self) -> &'static str {
+        "docx"
+    }
+
+    fn name(&self) -> &'static str {
+        "Word document"
+    }
+
+    fn extension(&self) -> &'static str {
+        "docx"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        _options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_docx(doc, output_dir, progress)
+    }
+}
+
+pub fn generate_docx(
+    doc: &CompiledDocument,
+    output_dir: &Path,
+    progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, String> {
+    let total_steps = doc.chapters.len() + 2;
+
+    progress("Preparing document...", 0, total_steps);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create exports directory: {}", e))?;
+
+    let output_path = output_dir.join(output_file_name(&doc.title, "docx"));
+    let file =
+        File::create(&output_path).map_err(|e| format!("Failed to create DOCX file: {}", e))?;
+
+    write_docx(doc, file, progress, total_steps)?;
+
+    Ok(output_path)
+}
+
+fn write_docx<W: Write + Seek>(
+    doc: &CompiledDocument,
+    writer: W,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> Result<(), String> {
+    // Word's own default, rather than the bare "en" used by ebooks
+    let language = doc.language_tag().unwrap_or("en-US");
+
+    let mut body = String::new();
+
+    // === Title Page ===
+    body.push_str(&plain_paragraph(
+        &doc.title,
+        ParagraphProps {
+            style: Some("Title"),
+            ..Default::default()
+        },
+    ));
+    if !doc.author.trim().is_empty() {
+        body.push_str(&plain_paragraph(
+            &doc.author,
+            ParagraphProps {
+                style: Some("Subtitle"),
+                ..Default::default()
+            },
+        ));
+    }
+
+    // === Front Matter ===
+    body.push_str(&render_matter(doc.front_matter.as_deref()));
+
+    // === Chapters ===
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+        body.push_str(&render_chapter(chapter));
+    }
+
+    // === Back Matter ===
+    body.push_str(&render_matter(doc.back_matter.as_deref()));
+
+    progress("Writing DOCX file...", total_steps, total_steps);
+
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <w:document xmlns:w=\"{}\"><w:body>{}{}</w:body></w:document>\n",
+        WORD_NS, body, SECTION_PROPERTIES
+    );
+
+    let files = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", PACKAGE_RELS.to_string()),
+        ("docProps/core.xml", core_properties(doc)),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/document.xml", document),
+        ("word/styles.xml", styles(language)),
+        ("word/numbering.xml", NUMBERING.to_string()),
+    ];
+
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, content) in files {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Failed to write DOCX: {}", e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write DOCX: {}", e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write DOCX: {}", e))?;
+
+    Ok(())
+}
+
+fn core_properties(doc: &CompiledDocument) -> String {
+    let now = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let mut properties = format!("<dc:title>{}</dc:title>", escape_xml(&doc.title));
+    if !doc.author.trim().is_empty() {
+        properties.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            escape_xml(doc.author.trim())
+        ));
+    }
+    if let Some(identifier) = doc.identifier.as_deref().filter(|i| !i.trim().is_empty()) {
+        properties.push_str(&format!(
+            "<dc:identifier>{}</dc:identifier>",
+            escape_xml(identifier.trim())
+        ));
+    }
+    if let Some(language) = doc.language_tag() {
+        properties.push_str(&format!(
+            "<dc:language>{}</dc:language>",
+            escape_xml(language)
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">{properties}<dcterms:created xsi:type="dcterms:W3CDTF">{now}</dcterms:created><dcterms:modified xsi:type="dcterms:W3CDTF">{now}</dcterms:modified></cp:coreProperties>
+"#
+    )
+}
+
+// Manuscript defaults: 12pt Times New Roman, 1.5 line spacing. Heading 1-3
+// are the built-in style ids, so Word's navigation pane and TOC pick them up.
+fn styles(language: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="{WORD_NS}">
+  <w:docDefaults>
+    <w:rPrDefault><w:rPr><w:rFonts w:ascii="Times New Roman" w:eastAsia="Times New Roman" w:hAnsi="Times New Roman" w:cs="Times New Roman"/><w:sz w:val="24"/><w:szCs w:val="24"/><w:lang w:val="{language}"/></w:rPr></w:rPrDefault>
+    <w:pPrDefault><w:pPr><w:spacing w:after="160" w:line="360" w:lineRule="auto"/></w:pPr></w:pPrDefault>
+  </w:docDefaults>
+  <w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/></w:style>
+  <w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:spacing w:before="2880" w:after="480"/><w:jc w:val="center"/></w:pPr><w:rPr><w:b/><w:bCs/><w:sz w:val="56"/><w:szCs w:val="56"/></w:rPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Subtitle"><w:name w:val="Subtitle"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:jc w:val="center"/></w:pPr><w:rPr><w:sz w:val="32"/><w:szCs w:val="32"/></w:rPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="1440" w:after="480"/><w:jc w:val="center"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:b/><w:bCs/><w:sz w:val="44"/><w:szCs w:val="44"/></w:rPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="360" w:after="240"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:b/><w:bCs/><w:sz w:val="28"/><w:szCs w:val="28"/></w:rPr></w:style>
+  <w:style w:type="paragraph" w:styleId="Heading3"><w:name w:val="heading 3"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="120"/><w:outlineLvl w:val="2"/></w:pPr><w:rPr><w:b/><w:bCs/><w:sz w:val="26"/><w:szCs w:val="26"/></w:rPr></w:style>
+  <w:style w:type="paragraph" w:styleId="ListParagraph"><w:name w:val="List Paragraph"/><w:basedOn w:val="Normal"/><w:qFormat/><w:pPr><w:ind w:left="720"/><w:contextualSpacing/></w:pPr></w:style>
+</w:styles>
+"#,
+        language = escape_xml(language),
+    )
+}
+
+// A <w:p>. Child elements of <w:pPr> must appear in schema order or Word
+// refuses to open the file.
+fn paragraph(runs: &str, props: ParagraphProps) -> String {
+    let mut p_pr = String::new();
+    if let Some(style) = props.style {
+        p_pr.push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+    }
+    if props.page_break_before {
+        p_pr.push_str("<w:pageBreakBefore/>");
+    }
+    if props.bullet {
+        p_pr.push_str(&format!(
+            "<w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"{}\"/></w:numPr>",
+            BULLET_NUM_ID
+        ));
+    }
+
+    if p_pr.is_empty() {
+        format!("<w:p>{}</w:p>", runs)
+    } else {
+        format!("<w:p><w:pPr>{}</w:pPr>{}</w:p>", p_pr, runs)
+    }
+}
+
+fn plain_paragraph(text: &str, props: ParagraphProps) -> String {
+    paragraph(&text_run(text.trim(), false, false), props)
+}
+
+fn text_run(text: &str, bold: bool, italic: bool) -> String {
+    let mut r_pr = String::new();
+    if bold {
+        r_pr.push_str("<w:b/><w:bCs/>");
+    }
+    if italic {
+        r_pr.push_str("<w:i/><w:iCs/>");
+    }
+    let r_pr = if r_pr.is_empty() {
+        r_pr
+    } else {
+        format!("<w:rPr>{}</w:rPr>", r_pr)
+    };
+    format!(
+        "<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        r_pr,
+        escape_xml(text)
+    )
+}
+
+fn render_runs(runs: &[&TextElement]) -> String {
+    runs.iter()
+        .map(|run| {
+            if run.text == "\n" {
+                "<w:r><w:br/></w:r>".to_string()
+            } else {
+                text_run(&run.text, run.bold, run.italic)
+            }
+        })
+        .collect()
+}
+
+// Front or back matter, starting on a new page
+fn render_matter(text: Option<&str>) -> String {
+    matter_paragraphs(text)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            plain_paragraph(
+                line,
+                ParagraphProps {
+                    page_break_before: i == 0,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+fn render_chapter(chapter: &Chapter) -> String {
+    let mut out = plain_paragraph(
+        &chapter.title,
+        ParagraphProps {
+            style: Some("Heading1"),
+            page_break_before: true,
+            ..Default::default()
+        },
+    );
+
+    for section in &chapter.sections {
+        out.push_str(&plain_paragraph(
+            &section.title,
+            ParagraphProps {
+                style: Some("Heading2"),
+                ..Default::default()
+            },
+        ));
+
+        for block in group_blocks(&section.elements) {
+            match block {
+                Block::Paragraph(runs) => {
+                    if runs.iter().all(|r| r.text.trim().is_empty()) {
+                        continue;
+                    }
+                    out.push_str(&paragraph(&render_runs(&runs), ParagraphProps::default()));
+                }
+                Block::Heading(runs) => {
+                    out.push_str(&paragraph(
+                        &render_runs(&runs),
+                        ParagraphProps {
+                            style: Some("Heading3"),
+                            ..Default::default()
+                        },
+                    ));
+                }
+                Block::List(items) => {
+                    for item in items {
+                        out.push_str(&paragraph(
+                            &render_runs(&item),
+                            ParagraphProps {
+                                style: Some("ListParagraph"),
+                                bullet: true,
+                                ..Default::default()
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::test_support::{document, node};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    fn make_doc() -> CompiledDocument {
+        CompiledDocument {
+            back_matter: Some("The End".to_string()),
+            ..document(&[
+                node(
+                    1,
+                    0,
+                    "Opening",
+                    Some(
+                        "<h2>Morning</h2><p>Some <strong>bold</strong> and <em>italic</em></p>\
+                         <ul><li>Buy <strong>milk</strong></li><li>Two</li></ul>",
+                    ),
+                ),
+                node(2, 0, "Closing", Some("<p>Goodbye</p>")),
+            ])
+        }
+    }
+
+    fn read_part(name: &str) -> String {
+        let mut buffer = Cursor::new(Vec::new());
+        write_docx(&make_doc(), &mut buffer, &|_, _, _| {}, 0).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(buffer.into_inner())).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_chapters_use_heading_styles_and_page_breaks() {
+        let document = read_part("word/document.xml");
+        assert_eq!(
+            document
+                .matches("<w:pStyle w:val=\"Heading1\"/><w:pageBreakBefore/>")
+                .count(),
+            2
+        );
+        assert!(document.contains("<w:pStyle w:val=\"Heading2\"/>"));
+        assert!(document.contains(
+            "<w:pPr><w:pStyle w:val=\"Heading3\"/></w:pPr><w:r><w:t xml:space=\"preserve\">Morning</w:t></w:r>"
+        ));
+        assert!(document.contains("Tom &amp; Jerry"));
+        assert!(document.ends_with("</w:sectPr></w:body></w:document>\n"));
+    }
+
+    #[test]
+    fn test_runs_keep_bold_and_italic() {
+        let document = read_part("word/document.xml");
+        assert!(document.contains(
+            "<w:r><w:rPr><w:b/><w:bCs/></w:rPr><w:t xml:space=\"preserve\">bold</w:t></w:r>"
+        ));
+        assert!(document.contains(
+            "<w:r><w:rPr><w:i/><w:iCs/></w:rPr><w:t xml:space=\"preserve\">italic</w:t></w:r>"
+        ));
+    }
+
+    #[test]
+    fn test_list_items_are_numbered_paragraphs() {
+        let document = read_part("word/document.xml");
+        // One bulleted paragraph per item, however many runs it has
+        assert_eq!(document.matches("<w:numId w:val=\"1\"/>").count(), 2);
+        assert!(document.contains(
+            "<w:t xml:space=\"preserve\">Buy </w:t></w:r><w:r><w:rPr><w:b/><w:bCs/></w:rPr><w:t xml:space=\"preserve\">milk</w:t>"
+        ));
+        assert!(read_part("word/numbering.xml").contains("<w:num w:numId=\"1\">"));
+        assert!(read_part("[Content_Types].xml").contains("/word/numbering.xml"));
+    }
+}
@@ -0,0 +1,542 @@
+use std::fs::{self, File};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::export::compiler::{
+    group_blocks, matter_paragraphs, Block, Chapter, CompiledDocument, TextElement,
+};
+use crate::export::exporter::Exporter;
+use crate::export::types::FormatOptions;
+use crate::export::{escape_xml, output_file_name, render_html_runs};
+
+const STYLESHEET: &str = "body {
+  font-family: serif;
+  line-height: 1.5;
+  margin: 0 5%;
+}
+h1 {
+  text-align: center;
+  margin: 3em 0 1.5em;
+  page-break-before: always;
+}
+h2 {
+  margin: 2em 0 1em;
+}
+h3 {
+  margin: 1.5em 0 0.75em;
+}
+p {
+  margin: 0 0 0.75em;
+  text-indent: 1.5em;
+}
+section + section {
+  page-break-before: always;
+}
+.title-page {
+  text-align: center;
+  margin-top: 30%;
+}
+.title-page p {
+  text-indent: 0;
+}
+.book-title {
+  font-size: 2em;
+  font-weight: bold;
+}
+.book-author {
+  font-size: 1.25em;
+  margin-top: 1.5em;
+}
+";
+
+// One XHTML file in the spine, with its entries in the table of contents
+struct Page {
+    id: String,
+    href: String,
+    title: String,
+    body: String,
+    // Section labels and fragment ids shown under the page in the TOC
+    toc_children: Vec<(String, String)>,
+}
+
+pub struct EpubExporter;
+
+impl Exporter for EpubExporter {
+    fn id(&self) -> &'static str {
+        "epub"
+    }
+
+    fn name(&self) -> &'static str {
+        "EPUB"
+    }
+
+    fn extension(&self) -> &'static str {
+        "epub"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        _options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_epub(doc, output_dir, progress)
+    }
+}
+
+pub fn generate_epub(
+    doc: &CompiledDocument,
+    output_dir: &Path,
+    progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, String> {
+    let total_steps = doc.chapters.len() + 2;
+
+    progress("Preparing document...", 0, total_steps);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create exports directory: {}", e))?;
+
+    let output_path = output_dir.join(output_file_name(&doc.title, "epub"));
+    let file =
+        File::create(&output_path).map_err(|e| format!("Failed to create EPUB file: {}", e))?;
+
+    write_epub(doc, file, progress, total_steps)?;
+
+    Ok(output_path)
+}
+
+fn write_epub<W: Write + Seek>(
+    doc: &CompiledDocument,
+    writer: W,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> Result<(), String> {
+    let language = doc.language_or_default();
+    let identifier = doc
+        .identifier
+        .as_deref()
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(|i| i.to_string())
+        .unwrap_or_else(|| format!("urn:uuid:{}", uuid::Uuid::new_v4()));
+    let title = if doc.title.trim().is_empty() {
+        "Untitled"
+    } else {
+        doc.title.trim()
+    };
+
+    // === Pages in reading order ===
+    let mut pages = vec![Page {
+        id: "title-page".to_string(),
+        href: "title.xhtml".to_string(),
+        title: title.to_string(),
+        body: render_title_page(title, &doc.author),
+        toc_children: Vec::new(),
+    }];
+
+    if let Some(body) = render_matter(doc.front_matter.as_deref()) {
+        pages.push(Page {
+            id: "front-matter".to_string(),
+            href: "front-matter.xhtml".to_string(),
+            title: "Front Matter".to_string(),
+            body,
+            toc_children: Vec::new(),
+        });
+    }
+
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+        pages.push(chapter_page(i, chapter));
+    }
+
+    if let Some(body) = render_matter(doc.back_matter.as_deref()) {
+        pages.push(Page {
+            id: "back-matter".to_string(),
+            href: "back-matter.xhtml".to_string(),
+            title: "Back Matter".to_string(),
+            body,
+            toc_children: Vec::new(),
+        });
+    }
+
+    progress("Writing EPUB file...", total_steps, total_steps);
+
+    // === Package ===
+    let mut zip = ZipWriter::new(writer);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must come first, uncompressed
+    let mut files: Vec<(String, String)> = vec![
+        ("META-INF/container.xml".to_string(), container_xml()),
+        (
+            "OEBPS/content.opf".to_string(),
+            package_opf(title, &doc.author, language, &identifier, &pages),
+        ),
+        (
+            "OEBPS/nav.xhtml".to_string(),
+            nav_xhtml(title, language, &pages),
+        ),
+        (
+            "OEBPS/toc.ncx".to_string(),
+            toc_ncx(title, language, &identifier, &pages),
+        ),
+        ("OEBPS/style.css".to_string(), STYLESHEET.to_string()),
+    ];
+    for page in &pages {
+        files.push((
+            format!("OEBPS/{}", page.href),
+            xhtml_document(&page.title, language, &page.body),
+        ));
+    }
+
+    zip.start_file("mimetype", stored)
+        .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+    zip.write_all(b"application/epub+zip")
+        .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+    for (name, content) in files {
+        zip.start_file(name, deflated)
+            .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+
+    Ok(())
+}
+
+fn container_xml() -> String {
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#
+    .to_string()
+}
+
+fn package_opf(
+    title: &str,
+    author: &str,
+    language: &str,
+    identifier: &str,
+    pages: &[Page],
+) -> String {
+    let modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let creator = if author.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            "    <dc:creator>{}</dc:creator>\n",
+            escape_xml(author.trim())
+        )
+    };
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for page in pages {
+        manifest.push_str(&format!(
+            "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            page.id, page.href
+        ));
+        spine.push_str(&format!("    <itemref idref=\"{}\"/>\n", page.id));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{language}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+{creator}    <dc:language>{language}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{manifest}  </manifest>
+  <spine toc="ncx">
+{spine}  </spine>
+</package>
+"#,
+        language = escape_xml(language),
+        identifier = escape_xml(identifier),
+        title = escape_xml(title),
+    )
+}
+
+fn nav_xhtml(title: &str, language: &str, pages: &[Page]) -> String {
+    let mut items = String::new();
+    for page in pages {
+        items.push_str(&format!(
+            "      <li><a href=\"{}\">{}</a>",
+            page.href,
+            escape_xml(&page.title)
+        ));
+        if !page.toc_children.is_empty() {
+            items.push_str("\n        <ol>\n");
+            for (label, fragment) in &page.toc_children {
+                items.push_str(&format!(
+                    "          <li><a href=\"{}#{}\">{}</a></li>\n",
+                    page.href,
+                    fragment,
+                    escape_xml(label)
+                ));
+            }
+            items.push_str("        </ol>\n      ");
+        }
+        items.push_str("</li>\n");
+    }
+
+    let body = format!(
+        "  <nav epub:type=\"toc\" id=\"toc\">\n    <h1>Contents</h1>\n    <ol>\n{}    </ol>\n  </nav>\n",
+        items
+    );
+    xhtml_document(title, language, &body)
+}
+
+fn toc_ncx(title: &str, language: &str, identifier: &str, pages: &[Page]) -> String {
+    let mut play_order = 0;
+    let mut points = String::new();
+    for page in pages {
+        play_order += 1;
+        points.push_str(&format!(
+            "    <navPoint id=\"navpoint-{0}\" playOrder=\"{0}\">\n      <navLabel><text>{1}</text></navLabel>\n      <content src=\"{2}\"/>\n",
+            play_order,
+            escape_xml(&page.title),
+            page.href
+        ));
+        for (label, fragment) in &page.toc_children {
+            play_order += 1;
+            points.push_str(&format!(
+                "      <navPoint id=\"navpoint-{0}\" playOrder=\"{0}\">\n        <navLabel><text>{1}</text></navLabel>\n        <content src=\"{2}#{3}\"/>\n      </navPoint>\n",
+                play_order,
+                escape_xml(label),
+                page.href,
+                fragment
+            ));
+        }
+        points.push_str("    </navPoint>\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1" xml:lang="{language}">
+  <head>
+    <meta name="dtb:uid" content="{identifier}"/>
+    <meta name="dtb:depth" content="2"/>
+    <meta name="dtb:totalPageCount" content="0"/>
+    <meta name="dtb:maxPageNumber" content="0"/>
+  </head>
+  <docTitle><text>{title}</text></docTitle>
+  <navMap>
+{points}  </navMap>
+</ncx>
+"#,
+        language = escape_xml(language),
+        identifier = escape_xml(identifier),
+        title = escape_xml(title),
+    )
+}
+
+fn xhtml_document(title: &str, language: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        language = escape_xml(language),
+        title = escape_xml(title),
+    )
+}
+
+fn render_title_page(title: &str, author: &str) -> String {
+    let mut body = format!(
+        "  <div class=\"title-page\">\n    <p class=\"book-title\">{}</p>\n",
+        escape_xml(title)
+    );
+    if !author.trim().is_empty() {
+        body.push_str(&format!(
+            "    <p class=\"book-author\">{}</p>\n",
+            escape_xml(author.trim())
+        ));
+    }
+    body.push_str("  </div>\n");
+    body
+}
+
+// Front or back matter as a page body, if there is any
+fn render_matter(text: Option<&str>) -> Option<String> {
+    let paragraphs = matter_paragraphs(text);
+    if paragraphs.is_empty() {
+        return None;
+    }
+    Some(
+        paragraphs
+            .into_iter()
+            .map(|line| format!("  <p>{}</p>\n", escape_xml(line)))
+            .collect(),
+    )
+}
+
+fn chapter_page(index: usize, chapter: &Chapter) -> Page {
+    let mut body = format!("  <h1>{}</h1>\n", escape_xml(&chapter.title));
+    let mut toc_children = Vec::new();
+
+    for (i, section) in chapter.sections.iter().enumerate() {
+        let fragment = format!("section-{}", i + 1);
+        body.push_str(&format!(
+            "  <section id=\"{}\">\n    <h2>{}</h2>\n",
+            fragment,
+            escape_xml(&section.title)
+        ));
+        body.push_str(&render_blocks(&group_blocks(&section.elements)));
+        body.push_str("  </section>\n");
+
+        // A lone section named like its chapter adds nothing to the TOC
+        if chapter.sections.len() > 1 || section.title != chapter.title {
+            toc_children.push((section.title.clone(), fragment));
+        }
+    }
+
+    Page {
+        id: format!("chapter-{:03}", index + 1),
+        href: format!("chapter-{:03}.xhtml", index + 1),
+        title: chapter.title.clone(),
+        body,
+        toc_children,
+    }
+}
+
+fn render_blocks(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Paragraph(runs) => {
+                if runs.iter().all(|r| r.text.trim().is_empty()) {
+                    continue;
+                }
+                out.push_str(&format!("    <p>{}</p>\n", render_runs(runs)));
+            }
+            Block::Heading(runs) => {
+                out.push_str(&format!("    <h3>{}</h3>\n", render_runs(runs)));
+            }
+            Block::List(items) => {
+                out.push_str("    <ul>\n");
+                for item in items {
+                    out.push_str(&format!("      <li>{}</li>\n", render_runs(item)));
+                }
+                out.push_str("    </ul>\n");
+            }
+        }
+    }
+    out
+}
+
+fn render_runs(runs: &[&TextElement]) -> String {
+    render_html_runs(runs, "<br/>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::test_support::{document, node};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    fn make_doc(html: &str) -> CompiledDocument {
+        CompiledDocument {
+            front_matter: Some("Copyright 2024".to_string()),
+            language: Some("en-GB".to_string()),
+            identifier: Some("isbn:9780000000000".to_string()),
+            ..document(&[node(1, 0, "Scene 1", Some(html))])
+        }
+    }
+
+    fn build(doc: &CompiledDocument) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut buffer = Cursor::new(Vec::new());
+        write_epub(doc, &mut buffer, &|_, _, _| {}, 0).unwrap();
+        ZipArchive::new(Cursor::new(buffer.into_inner())).unwrap()
+    }
+
+    fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_mimetype_is_first_and_stored() {
+        let mut archive = build(&make_doc("<p>Hello</p>"));
+        let first = archive.by_index(0).unwrap();
+        assert_eq!(first.name(), "mimetype");
+        assert_eq!(first.compression(), CompressionMethod::Stored);
+        drop(first);
+        assert_eq!(read_entry(&mut archive, "mimetype"), "application/epub+zip");
+    }
+
+    #[test]
+    fn test_package_metadata() {
+        let mut archive = build(&make_doc("<p>Hello</p>"));
+        let opf = read_entry(&mut archive, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>Tom &amp; Jerry</dc:title>"));
+        assert!(opf.contains("<dc:creator>Test Author</dc:creator>"));
+        assert!(opf.contains("<dc:language>en-GB</dc:language>"));
+        assert!(opf.contains("<dc:identifier id=\"book-id\">isbn:9780000000000</dc:identifier>"));
+        assert!(opf.contains("<itemref idref=\"front-matter\"/>"));
+        assert!(opf.contains("<itemref idref=\"chapter-001\"/>"));
+
+        let nav = read_entry(&mut archive, "OEBPS/nav.xhtml");
+        assert!(nav.contains("<a href=\"chapter-001.xhtml\">Scene 1</a>"));
+        let ncx = read_entry(&mut archive, "OEBPS/toc.ncx");
+        assert!(ncx.contains("<content src=\"chapter-001.xhtml\"/>"));
+    }
+
+    #[test]
+    fn test_chapter_keeps_formatting() {
+        let mut archive = build(&make_doc(
+            "<h2>Morning</h2><p>Some <strong>bold</strong> and <em>a &lt;tag&gt;</em><br>next</p>\
+             <ul><li>One</li><li>Two</li></ul>",
+        ));
+        let chapter = read_entry(&mut archive, "OEBPS/chapter-001.xhtml");
+        assert!(chapter.contains("<h1>Scene 1</h1>"));
+        assert!(chapter.contains("<h3>Morning</h3>"));
+        assert!(chapter
+            .contains("<p>Some <strong>bold</strong> and <em>a &lt;tag&gt;</em><br/>next</p>"));
+        assert!(chapter.contains("<ul>\n      <li>One</li>\n      <li>Two</li>\n    </ul>"));
+    }
+
+    #[test]
+    fn test_list_item_with_several_runs_is_one_item() {
+        let mut archive = build(&make_doc(
+            "<ul><li>Buy <strong>milk</strong></li><li>Bread</li></ul>",
+        ));
+        let chapter = read_entry(&mut archive, "OEBPS/chapter-001.xhtml");
+        assert!(chapter.contains(
+            "<ul>\n      <li>Buy <strong>milk</strong></li>\n      <li>Bread</li>\n    </ul>"
+        ));
+    }
+}
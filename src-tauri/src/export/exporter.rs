@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::export::compiler::CompiledDocument;
+use crate::export::docx_adapter::DocxExporter;
+use crate::export::epub_adapter::EpubExporter;
+use crate::export::html_adapter::HtmlExporter;
+use crate::export::markdown_adapter::MarkdownExporter;
+use crate::export::pdf_adapter::PdfExporter;
+use crate::export::text_adapter::TextExporter;
+use crate::export::types::{ExportFormat, FormatOptions};
+
+// An output format. Exporters only render the compiled document; compiling
+// and reporting the result are shared in `export_project`.
+pub trait Exporter: Send + Sync {
+    // Matches `ExportPayload.format`
+    fn id(&self) -> &'static str;
+    // Shown in the export dialog
+    fn name(&self) -> &'static str;
+    fn extension(&self) -> &'static str;
+    // Write the document into `output_dir` and return the file's path.
+    // `options` holds settings specific to this format; `progress` is
+    // called with (stage, current, total).
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String>;
+}
+
+// Every available format, in the order the export dialog lists them
+pub fn exporters() -> Vec<Box<dyn Exporter>> {
+    vec![
+        Box::new(PdfExporter),
+        Box::new(EpubExporter),
+        Box::new(DocxExporter),
+        Box::new(HtmlExporter),
+        Box::new(MarkdownExporter),
+        Box::new(TextExporter),
+    ]
+}
+
+// Read an exporter's own options; unknown keys are ignored
+pub fn parse_format_options<T: DeserializeOwned>(options: &FormatOptions) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::Object(options.clone()))
+        .map_err(|e| format!("Invalid export options: {}", e))
+}
+
+pub fn find_exporter(format: &str) -> Result<Box<dyn Exporter>, String> {
+    exporters()
+        .into_iter()
+        .find(|e| e.id() == format)
+        .ok_or_else(|| format!("Unknown export format: {}", format))
+}
+
+// The format that writes files with this extension
+pub fn format_for_extension(extension: &str) -> Option<&'static str> {
+    exporters()
+        .into_iter()
+        .find(|e| e.extension().eq_ignore_ascii_case(extension))
+        .map(|e| e.id())
+}
+
+pub fn export_formats() -> Vec<ExportFormat> {
+    exporters()
+        .iter()
+        .map(|e| ExportFormat {
+            id: e.id().to_string(),
+            name: e.name().to_string(),
+            extension: e.extension().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_formats_are_unique() {
+        let formats = export_formats();
+        let ids: HashSet<_> = formats.iter().map(|f| f.id.as_str()).collect();
+        let extensions: HashSet<_> = formats.iter().map(|f| f.extension.as_str()).collect();
+        assert_eq!(ids.len(), formats.len());
+        assert_eq!(extensions.len(), formats.len());
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(find_exporter("epub").unwrap().extension(), "epub");
+        assert!(find_exporter("rtf").is_err());
+        assert_eq!(format_for_extension("DOCX"), Some("docx"));
+        assert_eq!(format_for_extension("md"), Some("markdown"));
+        assert_eq!(format_for_extension("tmp"), None);
+    }
+}
@@ -0,0 +1,483 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::export::compiler::{
+    group_blocks, is_scene_break, matter_paragraphs, Block, Chapter, CompiledDocument, TextElement,
+};
+use crate::export::exporter::{parse_format_options, Exporter};
+use crate::export::types::FormatOptions;
+use crate::export::{escape_xml, output_file_name, render_html_runs};
+
+const INDEX_FILE: &str = "index.html";
+
+const STYLESHEET: &str = "
+body {
+  font-family: Georgia, 'Times New Roman', serif;
+  font-size: 1.125rem;
+  line-height: 1.6;
+  color: #222;
+  background: #fdfdfb;
+  max-width: 40em;
+  margin: 0 auto;
+  padding: 2em 1.25em;
+}
+a { color: #2a5d9f; }
+.title-page { text-align: center; margin: 4em 0; }
+.book-title { font-size: 2.5em; margin: 0; }
+.book-author { font-size: 1.25em; font-style: italic; }
+nav.toc ol { list-style: none; padding-left: 0; }
+nav.toc ol ol { padding-left: 1.5em; }
+nav.toc li { margin: 0.25em 0; }
+.chapter > h2 { text-align: center; margin: 3em 0 1.5em; }
+.scene > h3 { margin: 2em 0 1em; }
+p { margin: 0 0 1em; text-indent: 1.5em; }
+h2 + p, h3 + p, h4 + p, hr + p, .matter p { text-indent: 0; }
+hr.scene-break { border: 0; margin: 1.5em 0; text-align: center; }
+hr.scene-break::after { content: '* * *'; letter-spacing: 0.5em; }
+.chapter-nav {
+  display: flex;
+  justify-content: space-between;
+  margin: 2em 0;
+  font-family: sans-serif;
+  font-size: 0.9em;
+}
+@media print {
+  body { background: none; color: #000; font-size: 12pt; max-width: none; padding: 0; }
+  a { color: inherit; text-decoration: none; }
+  .chapter-nav { display: none; }
+  nav.toc, .chapter { break-before: page; page-break-before: always; }
+  h2, h3, h4 { break-after: avoid; page-break-after: avoid; }
+  p { orphans: 3; widows: 3; }
+}
+@page { margin: 2cm; }
+";
+
+pub struct HtmlExporter;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HtmlOptions {
+    // One page per chapter with previous/next links
+    pub split_chapters: bool,
+}
+
+impl Exporter for HtmlExporter {
+    fn id(&self) -> &'static str {
+        "html"
+    }
+
+    fn name(&self) -> &'static str {
+        "HTML"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_html(doc, &parse_format_options(options)?, output_dir, progress)
+    }
+}
+
+// One self-contained page, or with `split_chapters` a folder holding an
+// index page and one page per chapter. Returns the page to open.
+pub fn generate_html(
+    doc: &CompiledDocument,
+    options: &HtmlOptions,
+    output_dir: &Path,
+    progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, String> {
+    let total_steps = doc.chapters.len() + 2;
+
+    progress("Preparing document...", 0, total_steps);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create exports directory: {}", e))?;
+
+    let file_name = output_file_name(&doc.title, "html");
+
+    if !options.split_chapters {
+        let html = render_single_page(doc, progress, total_steps);
+        let output_path = output_dir.join(file_name);
+
+        progress("Writing HTML file...", total_steps, total_steps);
+
+        fs::write(&output_path, html).map_err(|e| format!("Failed to write HTML file: {}", e))?;
+        return Ok(output_path);
+    }
+
+    let pages = render_chapter_pages(doc, progress, total_steps);
+    let folder = create_unique_folder(output_dir, file_name.trim_end_matches(".html"))?;
+
+    progress("Writing HTML files...", total_steps, total_steps);
+
+    write_pages(&folder, pages)?;
+
+    Ok(folder.join(INDEX_FILE))
+}
+
+// Create a new folder named `name`, or `name_2`, `name_3` and so on if it
+// is taken, so an export never mixes with the pages of an earlier one
+fn create_unique_folder(output_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut counter = 1;
+    loop {
+        let folder = if counter == 1 {
+            output_dir.join(name)
+        } else {
+            output_dir.join(format!("{}_{}", name, counter))
+        };
+        match fs::create_dir(&folder) {
+            Ok(()) => return Ok(folder),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(format!("Failed to create HTML export folder: {}", e)),
+        }
+    }
+}
+
+fn write_pages(folder: &Path, pages: Vec<(String, String)>) -> Result<(), String> {
+    for (name, html) in pages {
+        fs::write(folder.join(&name), html)
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+fn chapter_id(index: usize) -> String {
+    format!("chapter-{}", index + 1)
+}
+
+fn section_id(chapter: usize, section: usize) -> String {
+    format!("chapter-{}-section-{}", chapter + 1, section + 1)
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{:03}.html", index + 1)
+}
+
+fn html_page(doc: &CompiledDocument, title: &str, body: &str) -> String {
+    let author = if doc.author.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n<meta name=\"author\" content=\"{}\">",
+            escape_xml(doc.author.trim())
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{language}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">{author}
+<title>{title}</title>
+<style>{STYLESHEET}</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        language = escape_xml(doc.language_or_default()),
+        title = escape_xml(title),
+    )
+}
+
+fn title_page(doc: &CompiledDocument) -> String {
+    let mut out = format!(
+        "<header class=\"title-page\">\n<h1 class=\"book-title\">{}</h1>\n",
+        escape_xml(doc.title.trim())
+    );
+    if !doc.author.trim().is_empty() {
+        out.push_str(&format!(
+            "<p class=\"book-author\">{}</p>\n",
+            escape_xml(doc.author.trim())
+        ));
+    }
+    out.push_str("</header>\n");
+    out
+}
+
+// Front or back matter as a section with the given class
+fn render_matter(text: Option<&str>, class: &str) -> String {
+    let paragraphs: String = matter_paragraphs(text)
+        .into_iter()
+        .map(|line| format!("<p>{}</p>\n", escape_xml(line)))
+        .collect();
+    if paragraphs.is_empty() {
+        return String::new();
+    }
+    format!(
+        "<section class=\"matter {}\">\n{}</section>\n",
+        class, paragraphs
+    )
+}
+
+// The contents list. `chapter_href` gives the link to a chapter; sections
+// link to their anchor within it.
+fn table_of_contents(doc: &CompiledDocument, chapter_href: &dyn Fn(usize) -> String) -> String {
+    let mut out = String::from("<nav class=\"toc\" id=\"contents\">\n<h2>Contents</h2>\n<ol>\n");
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        let href = chapter_href(i);
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            href,
+            escape_xml(&chapter.title)
+        ));
+
+        // A lone section named like its chapter adds nothing
+        let lone = chapter.sections.len() == 1 && chapter.sections[0].title == chapter.title;
+        if !chapter.sections.is_empty() && !lone {
+            // Drop the chapter's own fragment so section anchors resolve
+            let page = href.split('#').next().unwrap_or("");
+            out.push_str("\n<ol>\n");
+            for (j, section) in chapter.sections.iter().enumerate() {
+                out.push_str(&format!(
+                    "<li><a href=\"{}#{}\">{}</a></li>\n",
+                    page,
+                    section_id(i, j),
+                    escape_xml(&section.title)
+                ));
+            }
+            out.push_str("</ol>\n");
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ol>\n</nav>\n");
+    out
+}
+
+fn render_chapter(index: usize, chapter: &Chapter) -> String {
+    let mut out = format!(
+        "<article class=\"chapter\" id=\"{}\">\n<h2>{}</h2>\n",
+        chapter_id(index),
+        escape_xml(&chapter.title)
+    );
+
+    for (j, section) in chapter.sections.iter().enumerate() {
+        out.push_str(&format!(
+            "<section class=\"scene\" id=\"{}\">\n<h3>{}</h3>\n",
+            section_id(index, j),
+            escape_xml(&section.title)
+        ));
+        for block in group_blocks(&section.elements) {
+            match block {
+                Block::Paragraph(runs) => {
+                    if is_scene_break(&runs) {
+                        out.push_str("<hr class=\"scene-break\">\n");
+                    } else if !runs.iter().all(|r| r.text.trim().is_empty()) {
+                        out.push_str(&format!("<p>{}</p>\n", render_runs(&runs)));
+                    }
+                }
+                Block::Heading(runs) => {
+                    out.push_str(&format!("<h4>{}</h4>\n", render_runs(&runs)));
+                }
+                Block::List(items) => {
+                    out.push_str("<ul>\n");
+                    for item in items {
+                        out.push_str(&format!("<li>{}</li>\n", render_runs(&item)));
+                    }
+                    out.push_str("</ul>\n");
+                }
+            }
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</article>\n");
+    out
+}
+
+fn render_runs(runs: &[&TextElement]) -> String {
+    render_html_runs(runs, "<br>")
+}
+
+fn render_single_page(
+    doc: &CompiledDocument,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> String {
+    let mut body = title_page(doc);
+    body.push_str(&render_matter(doc.front_matter.as_deref(), "front-matter"));
+    body.push_str(&table_of_contents(doc, &|i| format!("#{}", chapter_id(i))));
+
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+        body.push_str(&render_chapter(i, chapter));
+    }
+
+    body.push_str(&render_matter(doc.back_matter.as_deref(), "back-matter"));
+    html_page(doc, &doc.title, &body)
+}
+
+// Previous, contents and next links around a chapter
+fn chapter_nav(doc: &CompiledDocument, index: usize) -> String {
+    let previous = if index == 0 {
+        "<span></span>".to_string()
+    } else {
+        format!(
+            "<a href=\"{}\" rel=\"prev\">&larr; {}</a>",
+            chapter_file(index - 1),
+            escape_xml(&doc.chapters[index - 1].title)
+        )
+    };
+    let next = if index + 1 == doc.chapters.len() {
+        "<span></span>".to_string()
+    } else {
+        format!(
+            "<a href=\"{}\" rel=\"next\">{} &rarr;</a>",
+            chapter_file(index + 1),
+            escape_xml(&doc.chapters[index + 1].title)
+        )
+    };
+    format!(
+        "<nav class=\"chapter-nav\">\n{}\n<a href=\"{}\">Contents</a>\n{}\n</nav>\n",
+        previous, INDEX_FILE, next
+    )
+}
+
+// (file name, html) for the index page and each chapter page. Back matter
+// closes the last chapter's page.
+fn render_chapter_pages(
+    doc: &CompiledDocument,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> Vec<(String, String)> {
+    let mut index = title_page(doc);
+    index.push_str(&render_matter(doc.front_matter.as_deref(), "front-matter"));
+    index.push_str(&table_of_contents(doc, &chapter_file));
+    if doc.chapters.is_empty() {
+        index.push_str(&render_matter(doc.back_matter.as_deref(), "back-matter"));
+    }
+
+    let mut pages = vec![(INDEX_FILE.to_string(), html_page(doc, &doc.title, &index))];
+
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+
+        let nav = chapter_nav(doc, i);
+        let mut body = nav.clone();
+        body.push_str(&render_chapter(i, chapter));
+        if i + 1 == doc.chapters.len() {
+            body.push_str(&render_matter(doc.back_matter.as_deref(), "back-matter"));
+        }
+        body.push_str(&nav);
+
+        let title = format!("{} – {}", chapter.title, doc.title);
+        pages.push((chapter_file(i), html_page(doc, &title, &body)));
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::test_support::{document, node};
+
+    fn make_doc() -> CompiledDocument {
+        CompiledDocument {
+            back_matter: Some("The End".to_string()),
+            ..document(&[
+                node(1, 0, "Act One", None),
+                node(
+                    2,
+                    1,
+                    "Opening",
+                    Some("<p>Some <strong>bold</strong> and <em>italic</em></p><p>* * *</p>"),
+                ),
+                node(3, 1, "Later", Some("<ul><li>One</li></ul>")),
+                node(4, 0, "Act Two", None),
+                node(5, 4, "Finale", Some("<h2>Dawn</h2>")),
+            ])
+        }
+    }
+
+    #[test]
+    fn test_single_page() {
+        let html = render_single_page(&make_doc(), &|_, _, _| {}, 0);
+        assert!(html.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(html.contains("@media print"));
+        assert!(html.contains("<li><a href=\"#chapter-1\">Act One</a>"));
+        assert!(html.contains("<li><a href=\"#chapter-1-section-2\">Later</a></li>"));
+        assert!(html.contains("<article class=\"chapter\" id=\"chapter-2\">"));
+        assert!(html.contains("<p>Some <strong>bold</strong> and <em>italic</em></p>"));
+        assert!(html.contains("<hr class=\"scene-break\">"));
+        assert!(html.contains("<ul>\n<li>One</li>\n</ul>"));
+        assert!(html.contains("<h4>Dawn</h4>"));
+        assert!(html.contains("<section class=\"matter back-matter\">\n<p>The End</p>"));
+    }
+
+    #[test]
+    fn test_list_item_with_several_runs_is_one_item() {
+        let doc = document(&[node(
+            1,
+            0,
+            "Errands",
+            Some("<ul><li>Buy <strong>milk</strong></li><li>Bread</li></ul>"),
+        )]);
+        let html = render_chapter(0, &doc.chapters[0]);
+        assert!(html.contains("<ul>\n<li>Buy <strong>milk</strong></li>\n<li>Bread</li>\n</ul>"));
+    }
+
+    #[test]
+    fn test_chapter_pages() {
+        let pages = render_chapter_pages(&make_doc(), &|_, _, _| {}, 0);
+        let names: Vec<&str> = pages.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["index.html", "chapter-001.html", "chapter-002.html"]
+        );
+
+        let (_, index) = &pages[0];
+        assert!(index.contains("<li><a href=\"chapter-002.html\">Act Two</a>"));
+        assert!(index.contains("<a href=\"chapter-001.html#chapter-1-section-2\">"));
+
+        let (_, first) = &pages[1];
+        assert!(first.contains("<a href=\"chapter-002.html\" rel=\"next\">Act Two &rarr;</a>"));
+        assert!(!first.contains("rel=\"prev\""));
+        assert!(!first.contains("The End"));
+
+        let (_, last) = &pages[2];
+        assert!(last.contains("<a href=\"chapter-001.html\" rel=\"prev\">&larr; Act One</a>"));
+        assert!(!last.contains("rel=\"next\""));
+        assert!(last.contains("The End"));
+    }
+
+    #[test]
+    fn test_split_export_never_reuses_a_folder() {
+        let dir = std::env::temp_dir().join(format!("wm9000-html-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("Book")).unwrap();
+        fs::write(dir.join("Book").join(chapter_file(8)), "earlier").unwrap();
+
+        let folder = create_unique_folder(&dir, "Book").unwrap();
+        assert_eq!(folder, dir.join("Book_2"));
+        assert_eq!(
+            create_unique_folder(&dir, "Book").unwrap(),
+            dir.join("Book_3")
+        );
+
+        write_pages(&folder, render_chapter_pages(&make_doc(), &|_, _, _| {}, 0)).unwrap();
+        assert!(folder.join(INDEX_FILE).exists());
+        assert!(!folder.join(chapter_file(8)).exists());
+        // The earlier export is left as it was
+        assert!(dir.join("Book").join(chapter_file(8)).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::export::compiler::{
+    group_blocks, is_scene_break, matter_paragraphs, Block, Chapter, CompiledDocument, TextElement,
+};
+use crate::export::exporter::Exporter;
+use crate::export::output_file_name;
+use crate::export::types::FormatOptions;
+
+const SCENE_BREAK: &str = "* * *";
+
+pub struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn id(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn name(&self) -> &'static str {
+        "Markdown"
+    }
+
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        _options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_markdown(doc, output_dir, progress)
+    }
+}
+
+pub fn generate_markdown(
+    doc: &CompiledDocument,
+    output_dir: &Path,
+    progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, String> {
+    let total_steps = doc.chapters.len() + 2;
+
+    progress("Preparing document...", 0, total_steps);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create exports directory: {}", e))?;
+
+    let markdown = render_markdown(doc, progress, total_steps);
+    let output_path = output_dir.join(output_file_name(&doc.title, "md"));
+
+    progress("Writing Markdown file...", total_steps, total_steps);
+
+    fs::write(&output_path, markdown)
+        .map_err(|e| format!("Failed to write Markdown file: {}", e))?;
+
+    Ok(output_path)
+}
+
+fn render_markdown(
+    doc: &CompiledDocument,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> String {
+    // YAML metadata block, as read by Pandoc and most static-site generators
+    let mut blocks = vec![yaml_metadata(doc)];
+
+    blocks.extend(render_matter(doc.front_matter.as_deref()));
+
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+        blocks.extend(render_chapter(chapter));
+    }
+
+    let back_matter = render_matter(doc.back_matter.as_deref());
+    if !back_matter.is_empty() {
+        blocks.push(SCENE_BREAK.to_string());
+        blocks.extend(back_matter);
+    }
+
+    let mut out = blocks.join("\n\n");
+    out.push('\n');
+    out
+}
+
+fn yaml_string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.trim().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn yaml_metadata(doc: &CompiledDocument) -> String {
+    let mut lines = vec![
+        "---".to_string(),
+        format!("title: {}", yaml_string(&doc.title)),
+    ];
+    if !doc.author.trim().is_empty() {
+        lines.push(format!("author: {}", yaml_string(&doc.author)));
+    }
+    if let Some(language) = doc.language_tag() {
+        lines.push(format!("lang: {}", yaml_string(language)));
+    }
+    if let Some(identifier) = doc.identifier.as_deref().filter(|i| !i.trim().is_empty()) {
+        lines.push(format!("identifier: {}", yaml_string(identifier)));
+    }
+    lines.push("---".to_string());
+    lines.join("\n")
+}
+
+// Backslash-escape characters Markdown would read as formatting
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// A line starting like a heading, list item or rule would become one
+fn escape_line_start(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    if trimmed.starts_with(['#', '-', '+', '=']) {
+        return format!("{}\\{}", indent, trimmed);
+    }
+
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && trimmed[digits..].starts_with(['.', ')']) {
+        return format!("{}{}\\{}", indent, &trimmed[..digits], &trimmed[digits..]);
+    }
+
+    line.to_string()
+}
+
+// Adjacent runs with the same formatting, so "**a****b**" becomes "**ab**".
+// Line breaks stay separate runs.
+fn merge_runs(runs: &[&TextElement]) -> Vec<(String, bool, bool)> {
+    let mut merged: Vec<(String, bool, bool)> = Vec::new();
+    for run in runs {
+        if run.text == "\n" {
+            merged.push(("\n".to_string(), false, false));
+            continue;
+        }
+        // Newlines inside a run are source formatting, not line breaks
+        let text = run.text.replace('\n', " ");
+        match merged.last_mut() {
+            Some((last, bold, italic))
+                if last != "\n" && *bold == run.bold && *italic == run.italic =>
+            {
+                last.push_str(&text)
+            }
+            _ => merged.push((text, run.bold, run.italic)),
+        }
+    }
+    merged
+}
+
+fn render_runs(runs: &[&TextElement]) -> String {
+    let mut out = String::new();
+    for (text, bold, italic) in merge_runs(runs) {
+        if text == "\n" {
+            // A hard line break
+            out.push_str("\\\n");
+            continue;
+        }
+
+        let escaped = escape_markdown(&text);
+        let marker = match (bold, italic) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => "",
+        };
+        if marker.is_empty() || escaped.trim().is_empty() {
+            out.push_str(&escaped);
+            continue;
+        }
+
+        // Emphasis markers must hug the text, so surrounding spaces go outside
+        let start = escaped.len() - escaped.trim_start().len();
+        let end = escaped.trim_end().len();
+        out.push_str(&escaped[..start]);
+        out.push_str(marker);
+        out.push_str(&escaped[start..end]);
+        out.push_str(marker);
+        out.push_str(&escaped[end..]);
+    }
+
+    out.trim_end_matches("\\\n")
+        .lines()
+        .map(|line| escape_line_start(line.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_matter(text: Option<&str>) -> Vec<String> {
+    matter_paragraphs(text)
+        .into_iter()
+        .map(|line| escape_line_start(&escape_markdown(line)))
+        .collect()
+}
+
+fn render_chapter(chapter: &Chapter) -> Vec<String> {
+    let mut blocks = vec![format!("# {}", escape_markdown(chapter.title.trim()))];
+
+    for section in &chapter.sections {
+        blocks.push(format!("## {}", escape_markdown(section.title.trim())));
+
+        for block in group_blocks(&section.elements) {
+            match block {
+                Block::Paragraph(runs) => {
+                    if is_scene_break(&runs) {
+                        blocks.push(SCENE_BREAK.to_string());
+                    } else if !runs.iter().all(|r| r.text.trim().is_empty()) {
+                        blocks.push(render_runs(&runs));
+                    }
+                }
+                Block::Heading(runs) => {
+                    blocks.push(format!("### {}", render_runs(&runs).replace('\n', " ")));
+                }
+                Block::List(items) => {
+                    blocks.push(
+                        items
+                            .iter()
+                            .map(|item| format!("- {}", render_runs(item).replace('\n', "\n  ")))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::test_support::{document, node};
+
+    fn render(html: &str) -> String {
+        let doc = CompiledDocument {
+            title: "The \"Long\" Night".to_string(),
+            back_matter: Some("The End".to_string()),
+            language: Some("en".to_string()),
+            ..document(&[
+                node(1, 0, "Act One", None),
+                node(2, 1, "Opening", Some(html)),
+            ])
+        };
+        render_markdown(&doc, &|_, _, _| {}, 0)
+    }
+
+    #[test]
+    fn test_structure_and_metadata() {
+        let markdown = render("<h2>Morning</h2><p>It began.</p><p>* * *</p><p>Later.</p>");
+        assert_eq!(
+            markdown,
+            "---\ntitle: \"The \\\"Long\\\" Night\"\nauthor: \"Test Author\"\nlang: \"en\"\n---\n\n\
+             # Act One\n\n## Opening\n\n### Morning\n\nIt began.\n\n* * *\n\nLater.\n\n\
+             * * *\n\nThe End\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_formatting() {
+        let markdown = render(
+            "<p>Some <strong>bold </strong>and <em>italic</em>, <strong><em>both</em></strong>\
+             <br>next line</p><ul><li>One</li><li><em>Two</em></li></ul>",
+        );
+        assert!(markdown
+            .contains("Some **bold** and *italic*, ***both***\\\nnext line\n\n- One\n- *Two*\n"));
+    }
+
+    #[test]
+    fn test_list_item_with_several_runs_is_one_item() {
+        let markdown = render("<ul><li>Buy <strong>milk</strong></li><li>Bread</li></ul>");
+        assert!(markdown.contains("## Opening\n\n- Buy **milk**\n- Bread\n\n"));
+    }
+
+    #[test]
+    fn test_literal_markup_is_escaped() {
+        let markdown = render("<p># not a heading, *not* [a link]</p><p>1. Not a list</p>");
+        assert!(markdown.contains("\\# not a heading, \\*not\\* \\[a link\\]\n\n1\\. Not a list"));
+    }
+}
@@ -1,13 +1,101 @@
 pub mod compiler;
+pub mod docx_adapter;
+pub mod epub_adapter;
+pub mod exporter;
+pub mod html_adapter;
+pub mod markdown_adapter;
 pub mod pdf_adapter;
+pub mod text_adapter;
 pub mod types;
 
-use compiler::compile;
-use pdf_adapter::generate_pdf;
-use types::{ExportPayload, ExportResult};
+use std::path::PathBuf;
+
+use chrono::Local;
+use compiler::{compile, TextElement};
+use exporter::{export_formats, find_exporter, format_for_extension};
+use types::{ExportFormat, ExportPayload, ExportProgress, ExportResult};
 
 use tauri::AppHandle;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+fn emit_progress(app: &AppHandle, stage: &str, current: usize, total: usize) {
+    let _ = app.emit(
+        "export-progress",
+        ExportProgress {
+            stage: stage.to_string(),
+            current,
+            total,
+        },
+    );
+}
+
+// "<title>_<timestamp>.<extension>", with the title made safe for a path
+pub(crate) fn output_file_name(title: &str, extension: &str) -> String {
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let safe_title: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_{}.{}", safe_title.trim(), timestamp, extension)
+}
+
+// Escape text for XML content and attributes. Control characters other than
+// tab and newline are not allowed in XML 1.0 and are dropped.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Inline runs as HTML or XHTML, which differ only in how a line break is
+// written ("<br>" or "<br/>")
+pub(crate) fn render_html_runs(runs: &[&TextElement], line_break: &str) -> String {
+    let mut out = String::new();
+    for run in runs {
+        if run.text == "\n" {
+            out.push_str(line_break);
+            continue;
+        }
+        let mut text = escape_xml(&run.text);
+        if run.italic {
+            text = format!("<em>{}</em>", text);
+        }
+        if run.bold {
+            text = format!("<strong>{}</strong>", text);
+        }
+        out.push_str(&text);
+    }
+    out
+}
+
+fn exports_dir(app: &AppHandle, project_name: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+
+    Ok(app_data_dir
+        .join("Projects")
+        .join(project_name)
+        .join("exports"))
+}
 
 #[tauri::command]
 pub fn open_file_default(path: String) -> Result<(), String> {
@@ -40,6 +128,8 @@ pub struct ExportEntry {
     pub filename: String,
     pub path: String,
     pub modified: String,
+    // Id of the format that wrote the file
+    pub format: String,
 }
 
 #[tauri::command]
@@ -47,15 +137,7 @@ pub async fn list_project_exports(
     app: AppHandle,
     project_name: String,
 ) -> Result<Vec<ExportEntry>, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
-
-    let exports_dir = app_data_dir
-        .join("Projects")
-        .join(&project_name)
-        .join("exports");
+    let exports_dir = exports_dir(&app, &project_name)?;
 
     if !exports_dir.exists() {
         return Ok(vec![]);
@@ -67,13 +149,24 @@ pub async fn list_project_exports(
 
     for entry in read_dir {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) == Some("pdf") {
-            let filename = path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+        let mut path = entry.path();
+        let filename = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        // Exports of several files are folders; list them by their index page
+        if path.is_dir() {
+            path = path.join("index.html");
+            if !path.is_file() {
+                continue;
+            }
+        }
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(format_for_extension);
+        if let Some(format) = format {
             let modified = entry
                 .metadata()
                 .ok()
@@ -87,6 +180,7 @@ pub async fn list_project_exports(
                 filename,
                 path: path.to_string_lossy().to_string(),
                 modified,
+                format: format.to_string(),
             });
         }
     }
@@ -96,24 +190,22 @@ pub async fn list_project_exports(
     Ok(entries)
 }
 
+#[tauri::command]
+pub fn list_export_formats() -> Vec<ExportFormat> {
+    export_formats()
+}
+
 #[tauri::command]
 pub async fn export_project(
     app: AppHandle,
     payload: ExportPayload,
 ) -> Result<ExportResult, String> {
+    let exporter = find_exporter(&payload.format)?;
     let compiled = compile(&payload).map_err(|e| e.to_string())?;
+    let exports_dir = exports_dir(&app, &payload.project_name)?;
+    let progress = |stage: &str, current, total| emit_progress(&app, stage, current, total);
 
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
-
-    let exports_dir = app_data_dir
-        .join("Projects")
-        .join(&payload.project_name)
-        .join("exports");
-
-    match generate_pdf(&compiled, &exports_dir, Some(&app)) {
+    match exporter.export(&compiled, &payload.format_options, &exports_dir, &progress) {
         Ok(path) => Ok(ExportResult {
             success: true,
             output_path: Some(path.to_string_lossy().to_string()),
@@ -6,11 +6,10 @@ use genpdf::fonts::{self, FontData, FontFamily};
 use genpdf::style::Style;
 use genpdf::{Document, Element};
 
-use chrono::Local;
-use tauri::{AppHandle, Emitter};
-
 use crate::export::compiler::{BlockType, Chapter, CompiledDocument, Section, TextElement};
-use crate::export::types::ExportProgress;
+use crate::export::exporter::Exporter;
+use crate::export::output_file_name;
+use crate::export::types::FormatOptions;
 
 fn load_font_family() -> Result<FontFamily<FontData>, String> {
     // Try loading Liberation Serif (standard naming convention)
@@ -98,27 +97,40 @@ fn load_font_family() -> Result<FontFamily<FontData>, String> {
     Err("Could not find a suitable font (Times New Roman, Arial, or Liberation Serif).".to_string())
 }
 
-fn emit_progress(app: Option<&AppHandle>, stage: &str, current: usize, total: usize) {
-    if let Some(app) = app {
-        let _ = app.emit(
-            "export-progress",
-            ExportProgress {
-                stage: stage.to_string(),
-                current,
-                total,
-            },
-        );
+pub struct PdfExporter;
+
+impl Exporter for PdfExporter {
+    fn id(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn name(&self) -> &'static str {
+        "PDF"
+    }
+
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        _options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_pdf(doc, output_dir, progress)
     }
 }
 
 pub fn generate_pdf(
     doc: &CompiledDocument,
     output_dir: &Path,
-    app: Option<&AppHandle>,
+    progress: &dyn Fn(&str, usize, usize),
 ) -> Result<PathBuf, String> {
     let total_steps = doc.chapters.len() + 2; // +1 for compiling, +1 for writing file
 
-    emit_progress(app, "Preparing document...", 0, total_steps);
+    progress("Preparing document...", 0, total_steps);
 
     fs::create_dir_all(output_dir)
         .map_err(|e| format!("Failed to create exports directory: {}", e))?;
@@ -146,8 +158,7 @@ pub fn generate_pdf(
 
     // === Chapters ===
     for (i, chapter) in doc.chapters.iter().enumerate() {
-        emit_progress(
-            app,
+        progress(
             &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
             i + 1,
             total_steps,
@@ -163,23 +174,10 @@ pub fn generate_pdf(
         }
     }
 
-    // Generate filename with timestamp
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let safe_title: String = doc
-        .title
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
-                c
-            } else {
-                '_'
-            }
-        })
-        .collect();
-    let filename = format!("{}_{}.pdf", safe_title.trim(), timestamp);
+    let filename = output_file_name(&doc.title, "pdf");
     let output_path = output_dir.join(&filename);
 
-    emit_progress(app, "Writing PDF file...", total_steps, total_steps);
+    progress("Writing PDF file...", total_steps, total_steps);
 
     pdf.render_to_file(&output_path)
         .map_err(|e| format!("Failed to render PDF: {}", e))?;
@@ -226,6 +224,7 @@ fn render_section(pdf: &mut Document, section: &Section) {
 
     // Group elements into paragraphs
     let mut current_paragraph: Option<Paragraph> = None;
+    let mut current_item: Option<Paragraph> = None;
 
     for element in &section.elements {
         match element.block_type {
@@ -252,11 +251,19 @@ fn render_section(pdf: &mut Document, section: &Section) {
                     pdf.push(Break::new(0.3));
                 }
 
-                let bullet_text = format!("  - {}", element.text);
-                let mut para = Paragraph::default();
-                para.push_styled(bullet_text, build_style(element));
-                pdf.push(para);
-                pdf.push(Break::new(0.15));
+                // An item's runs share one bullet
+                let item = current_item.get_or_insert_with(|| {
+                    let mut para = Paragraph::default();
+                    para.push_styled("  - ", Style::new().with_font_size(12));
+                    para
+                });
+                item.push_styled(&element.text, build_style(element));
+            }
+            BlockType::ListItemBreak => {
+                if let Some(item) = current_item.take() {
+                    pdf.push(item);
+                    pdf.push(Break::new(0.15));
+                }
             }
             BlockType::Heading => {
                 if let Some(p) = current_paragraph.take() {
@@ -302,8 +309,6 @@ mod tests {
         CompiledDocument {
             title: "Test Book".to_string(),
             author: "Test Author".to_string(),
-            front_matter: None,
-            back_matter: None,
             chapters: vec![Chapter {
                 title: "Chapter 1".to_string(),
                 sections: vec![Section {
@@ -316,6 +321,7 @@ mod tests {
                     }],
                 }],
             }],
+            ..Default::default()
         }
     }
 
@@ -324,13 +330,11 @@ mod tests {
         let doc = CompiledDocument {
             title: "Empty".to_string(),
             author: "Nobody".to_string(),
-            front_matter: None,
-            back_matter: None,
-            chapters: vec![],
+            ..Default::default()
         };
         let tmp = env::temp_dir().join("wm9000_test_exports");
         // This may fail if no fonts are available in CI, that's OK
-        let _ = generate_pdf(&doc, &tmp, None);
+        let _ = generate_pdf(&doc, &tmp, &|_, _, _| {});
     }
 
     #[test]
@@ -340,9 +344,9 @@ mod tests {
             author: "Author and Co.".to_string(),
             front_matter: Some("Copyright 2024".to_string()),
             back_matter: Some("The End".to_string()),
-            chapters: vec![],
+            ..Default::default()
         };
         let tmp = env::temp_dir().join("wm9000_test_exports_special");
-        let _ = generate_pdf(&doc, &tmp, None);
+        let _ = generate_pdf(&doc, &tmp, &|_, _, _| {});
     }
 }
@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::export::compiler::{
+    group_blocks, is_scene_break, matter_paragraphs, Block, Chapter, CompiledDocument, TextElement,
+};
+use crate::export::exporter::{parse_format_options, Exporter};
+use crate::export::output_file_name;
+use crate::export::types::FormatOptions;
+
+const DEFAULT_LINE_WIDTH: usize = 72;
+const SCENE_BREAK: &str = "* * *";
+
+pub struct TextExporter;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TextOptions {
+    // Line length; 0 turns wrapping off. Defaults to 72.
+    pub line_width: Option<usize>,
+}
+
+impl Exporter for TextExporter {
+    fn id(&self) -> &'static str {
+        "text"
+    }
+
+    fn name(&self) -> &'static str {
+        "Plain text"
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(
+        &self,
+        doc: &CompiledDocument,
+        options: &FormatOptions,
+        output_dir: &Path,
+        progress: &dyn Fn(&str, usize, usize),
+    ) -> Result<PathBuf, String> {
+        generate_text(doc, &parse_format_options(options)?, output_dir, progress)
+    }
+}
+
+pub fn generate_text(
+    doc: &CompiledDocument,
+    options: &TextOptions,
+    output_dir: &Path,
+    progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, String> {
+    let total_steps = doc.chapters.len() + 2;
+
+    progress("Preparing document...", 0, total_steps);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create exports directory: {}", e))?;
+
+    let width = options.line_width.unwrap_or(DEFAULT_LINE_WIDTH);
+    let text = render_text(doc, width, progress, total_steps);
+    let output_path = output_dir.join(output_file_name(&doc.title, "txt"));
+
+    progress("Writing text file...", total_steps, total_steps);
+
+    fs::write(&output_path, text).map_err(|e| format!("Failed to write text file: {}", e))?;
+
+    Ok(output_path)
+}
+
+fn render_text(
+    doc: &CompiledDocument,
+    width: usize,
+    progress: &dyn Fn(&str, usize, usize),
+    total_steps: usize,
+) -> String {
+    let mut title_block = wrap(doc.title.trim(), width, "", "");
+    if !doc.author.trim().is_empty() {
+        title_block.push('\n');
+        title_block.push_str(&wrap(&format!("by {}", doc.author.trim()), width, "", ""));
+    }
+    let mut blocks = vec![title_block];
+
+    blocks.extend(render_matter(doc.front_matter.as_deref(), width));
+
+    for (i, chapter) in doc.chapters.iter().enumerate() {
+        progress(
+            &format!("Rendering chapter {} of {}...", i + 1, doc.chapters.len()),
+            i + 1,
+            total_steps,
+        );
+        blocks.extend(render_chapter(chapter, width));
+    }
+
+    let back_matter = render_matter(doc.back_matter.as_deref(), width);
+    if !back_matter.is_empty() {
+        blocks.push(SCENE_BREAK.to_string());
+        blocks.extend(back_matter);
+    }
+
+    let mut out = blocks.join("\n\n");
+    out.push('\n');
+    out
+}
+
+// Greedy word wrap. `first_indent` starts the first line and `rest_indent`
+// every other one. Hard line breaks in `text` are kept; a width of 0 only
+// applies the indents.
+fn wrap(text: &str, width: usize, first_indent: &str, rest_indent: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for hard_line in text.split('\n') {
+        let mut line = String::new();
+        let mut line_len = 0;
+        for word in hard_line.split_whitespace() {
+            let word_len = word.chars().count();
+            if line.is_empty() {
+                line.push_str(if lines.is_empty() {
+                    first_indent
+                } else {
+                    rest_indent
+                });
+                line_len = line.chars().count();
+            } else if width > 0 && line_len + 1 + word_len > width {
+                lines.push(line);
+                line = rest_indent.to_string();
+                line_len = line.chars().count();
+            } else {
+                line.push(' ');
+                line_len += 1;
+            }
+            line.push_str(word);
+            line_len += word_len;
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n")
+}
+
+// A title with a line of `underline` characters beneath it
+fn underlined(title: &str, underline: char, width: usize) -> String {
+    let title = wrap(title.trim(), width, "", "");
+    let longest = title.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+    format!(
+        "{}\n{}",
+        title,
+        underline.to_string().repeat(longest.max(1))
+    )
+}
+
+fn runs_text(runs: &[&TextElement]) -> String {
+    runs.iter()
+        .map(|run| {
+            if run.text == "\n" {
+                "\n".to_string()
+            } else {
+                // Newlines inside a run are source formatting, not line breaks
+                run.text.replace('\n', " ")
+            }
+        })
+        .collect()
+}
+
+fn render_matter(text: Option<&str>, width: usize) -> Vec<String> {
+    matter_paragraphs(text)
+        .into_iter()
+        .map(|line| wrap(line, width, "", ""))
+        .collect()
+}
+
+fn render_chapter(chapter: &Chapter, width: usize) -> Vec<String> {
+    // The extra newline leaves two blank lines before each chapter
+    let mut blocks = vec![format!("\n{}", underlined(&chapter.title, '=', width))];
+
+    for section in &chapter.sections {
+        blocks.push(underlined(&section.title, '-', width));
+
+        for block in group_blocks(&section.elements) {
+            match block {
+                Block::Paragraph(runs) => {
+                    if is_scene_break(&runs) {
+                        blocks.push(SCENE_BREAK.to_string());
+                    } else if !runs.iter().all(|r| r.text.trim().is_empty()) {
+                        blocks.push(wrap(&runs_text(&runs), width, "", ""));
+                    }
+                }
+                Block::Heading(runs) => {
+                    blocks.push(wrap(&runs_text(&runs), width, "", ""));
+                }
+                Block::List(items) => {
+                    blocks.push(
+                        items
+                            .iter()
+                            .map(|item| wrap(&runs_text(item), width, "  - ", "    "))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::compiler::test_support::{document, node};
+
+    fn render(html: &str, width: usize) -> String {
+        let doc = CompiledDocument {
+            title: "Night".to_string(),
+            ..document(&[node(1, 0, "Opening", Some(html))])
+        };
+        render_text(&doc, width, &|_, _, _| {}, 0)
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("the quick brown fox jumps", 10, "", ""),
+            "the quick\nbrown fox\njumps"
+        );
+        assert_eq!(
+            wrap("one two three", 9, "  - ", "    "),
+            "  - one\n    two\n    three"
+        );
+        assert_eq!(wrap("a\nb  c", 0, "", ""), "a\nb c");
+        // Words longer than the line are not split
+        assert_eq!(wrap("extraordinary", 5, "", ""), "extraordinary");
+    }
+
+    #[test]
+    fn test_layout() {
+        let text = render(
+            "<p>It was a <em>dark</em> and stormy night.</p><p>***</p><ul><li>One</li></ul>",
+            20,
+        );
+        assert_eq!(
+            text,
+            "Night\nby Test Author\n\n\nOpening\n=======\n\nOpening\n-------\n\n\
+             It was a dark and\nstormy night.\n\n* * *\n\n  - One\n"
+        );
+    }
+
+    #[test]
+    fn test_list_item_with_several_runs_is_one_item() {
+        let text = render(
+            "<ul><li>Buy <strong>milk</strong> and <em>eggs</em></li><li>Bread</li></ul>",
+            40,
+        );
+        assert!(text.ends_with("-------\n\n  - Buy milk and eggs\n  - Bread\n"));
+    }
+
+    #[test]
+    fn test_options() {
+        let mut options = FormatOptions::new();
+        let parsed: TextOptions = parse_format_options(&options).unwrap();
+        assert_eq!(parsed.line_width, None);
+
+        options.insert("line_width".to_string(), serde_json::json!(40));
+        let parsed: TextOptions = parse_format_options(&options).unwrap();
+        assert_eq!(parsed.line_width, Some(40));
+
+        options.insert("line_width".to_string(), serde_json::json!("wide"));
+        assert!(parse_format_options::<TextOptions>(&options).is_err());
+    }
+
+    #[test]
+    fn test_default_and_disabled_wrapping() {
+        let long = "word ".repeat(30);
+        let html = format!("<p>{}</p>", long);
+        assert!(render(&html, DEFAULT_LINE_WIDTH)
+            .lines()
+            .all(|line| line.chars().count() <= DEFAULT_LINE_WIDTH));
+        assert!(render(&html, 0).contains(long.trim()));
+    }
+}
@@ -15,6 +15,10 @@ pub struct ExportOptions {
     pub author: String,
     pub front_matter: Option<String>,
     pub back_matter: Option<String>,
+    // BCP 47 tag such as "en-GB"; ebook formats default to "en"
+    pub language: Option<String>,
+    // ISBN or other unique id; ebook formats generate one if unset
+    pub identifier: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -22,6 +26,19 @@ pub struct ExportPayload {
     pub project_name: String,
     pub nodes: Vec<ExportFileNode>,
     pub options: ExportOptions,
+    // Id of a registered exporter; see `list_export_formats`
+    #[serde(default = "default_format")]
+    pub format: String,
+    // Settings only the chosen format understands, such as the text
+    // exporter's "line_width" or the HTML exporter's "split_chapters"
+    #[serde(default)]
+    pub format_options: FormatOptions,
+}
+
+pub type FormatOptions = serde_json::Map<String, serde_json::Value>;
+
+fn default_format() -> String {
+    "pdf".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,3 +54,10 @@ pub struct ExportProgress {
     pub current: usize,
     pub total: usize,
 }
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFormat {
+    pub id: String,
+    pub name: String,
+    pub extension: String,
+}
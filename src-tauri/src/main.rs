@@ -5,24 +5,92 @@ mod ai;
 mod export;
 
 use dotenv::dotenv;
-use std::env;
-use tauri_plugin_fs; // To access environment variables
 
-use ai::proofread::proofread_content;
-use ai::review::ai_review;
-use ai::suggestions::ai_suggestions;
-use export::{export_project, list_project_exports, open_file_default};
+use ai::actions::{delete_ai_action, list_ai_actions, run_ai_action, save_ai_action};
+use ai::chat::{
+    append_chat_message, create_chat_session, delete_chat_session, get_chat_session,
+    list_chat_sessions,
+};
+use ai::common::cache::clear_ai_cache;
+use ai::common::privacy::{get_privacy_settings, save_privacy_settings};
+use ai::common::usage::get_ai_usage;
+use ai::credentials::{
+    clear_api_key, get_credential_status, set_api_key, test_api_key, unlock_api_keys,
+    CredentialKeys,
+};
+use ai::jobs::{cancel_ai_job, AiJobs};
+use ai::personas::{delete_persona, list_personas, run_persona_panel, save_persona};
+use ai::preview::preview_ai_request;
+use ai::project_review::ai_project_review;
+use ai::prompts::{
+    create_prompt_template, delete_prompt_template, export_prompt_templates,
+    import_prompt_templates, list_prompt_templates, set_active_prompt_template,
+    update_prompt_template,
+};
+use ai::proofread::{
+    proofread_content, proofread_content_stream, proofread_preserving_markup, proofread_structured,
+};
+use ai::review::{ai_review, ai_review_stream};
+use ai::settings::{
+    get_ai_command_config, get_ai_settings, save_ai_settings, set_ai_command_config,
+};
+use ai::suggestions::{ai_suggestions, ai_suggestions_stream};
+use export::{export_project, list_export_formats, list_project_exports, open_file_default};
 
 fn main() {
     dotenv().ok(); // Load the .env file
 
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
+        .manage(AiJobs::default())
+        .manage(CredentialKeys::default())
         .invoke_handler(tauri::generate_handler![
             proofread_content,
             ai_suggestions,
             ai_review,
+            proofread_content_stream,
+            proofread_structured,
+            proofread_preserving_markup,
+            ai_suggestions_stream,
+            ai_review_stream,
+            ai_project_review,
+            get_ai_settings,
+            save_ai_settings,
+            get_ai_command_config,
+            set_ai_command_config,
+            list_prompt_templates,
+            create_prompt_template,
+            update_prompt_template,
+            delete_prompt_template,
+            set_active_prompt_template,
+            export_prompt_templates,
+            import_prompt_templates,
+            list_ai_actions,
+            save_ai_action,
+            delete_ai_action,
+            run_ai_action,
+            cancel_ai_job,
+            get_ai_usage,
+            clear_ai_cache,
+            preview_ai_request,
+            get_privacy_settings,
+            save_privacy_settings,
+            create_chat_session,
+            list_chat_sessions,
+            get_chat_session,
+            append_chat_message,
+            delete_chat_session,
+            list_personas,
+            save_persona,
+            delete_persona,
+            run_persona_panel,
+            get_credential_status,
+            set_api_key,
+            unlock_api_keys,
+            clear_api_key,
+            test_api_key,
             export_project,
+            list_export_formats,
             list_project_exports,
             open_file_default
         ])
@@ -1,8 +1,53 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+interface AiJobEvent<T> {
+  job_id: string;
+  command: string;
+  status: "completed" | "failed" | "cancelled";
+  result?: T;
+  error?: { kind: string; message: string };
+}
+
+// AI commands return a job id straight away and report on the "ai-job"
+// event. The listener is attached before the job starts, so a job that
+// finishes at once (a cached response) is not missed.
+const runAiJob = async <T>(
+  command: string,
+  args: Record<string, unknown>,
+): Promise<T> => {
+  const early = new Map<string, AiJobEvent<T>>();
+  let jobId: string | null = null;
+  let settle: ((event: AiJobEvent<T>) => void) | null = null;
+
+  const unlisten = await listen<AiJobEvent<T>>("ai-job", ({ payload }) => {
+    if (jobId === null) {
+      early.set(payload.job_id, payload);
+    } else if (payload.job_id === jobId) {
+      settle?.(payload);
+    }
+  });
+
+  try {
+    jobId = await invoke<string>(command, args);
+    const event =
+      early.get(jobId) ??
+      (await new Promise<AiJobEvent<T>>((resolve) => {
+        settle = resolve;
+      }));
+
+    if (event.status !== "completed" || event.result === undefined) {
+      throw new Error(event.error?.message ?? `AI job ${event.status}`);
+    }
+    return event.result;
+  } finally {
+    unlisten();
+  }
+};
 
 export const aiProofreadContent = async (content: string): Promise<string> => {
   try {
-    const response = await invoke<{ result: string }>("proofread_content", {
+    const response = await runAiJob<{ result: string }>("proofread_content", {
       content,
     });
 
@@ -15,7 +60,7 @@ export const aiProofreadContent = async (content: string): Promise<string> => {
 
 export const aiGetSuggestions = async (content: string): Promise<string> => {
   try {
-    const response = await invoke<{ result: string }>("ai_suggestions", {
+    const response = await runAiJob<{ result: string }>("ai_suggestions", {
       content,
     });
 
@@ -28,7 +73,7 @@ export const aiGetSuggestions = async (content: string): Promise<string> => {
 
 export const aiGetReview = async (content: string): Promise<string> => {
   try {
-    const response = await invoke<{ result: string }>("ai_review", {
+    const response = await runAiJob<{ result: string }>("ai_review", {
       content,
     });
     return response.result;
@@ -11,12 +11,32 @@ export interface ExportOptions {
   author: string;
   front_matter?: string;
   back_matter?: string;
+  language?: string;
+  identifier?: string;
 }
 
 export interface ExportPayload {
   project_name: string;
   nodes: ExportFileNode[];
   options: ExportOptions;
+  // Id from list_export_formats; defaults to "pdf"
+  format?: string;
+  // Settings for the chosen format only
+  format_options?: FormatOptions;
+}
+
+export interface FormatOptions {
+  // Plain-text export; 0 turns wrapping off
+  line_width?: number;
+  // HTML export: one page per chapter with previous/next links
+  split_chapters?: boolean;
+  [key: string]: unknown;
+}
+
+export interface ExportFormat {
+  id: string;
+  name: string;
+  extension: string;
 }
 
 export interface ExportResult {